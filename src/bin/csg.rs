@@ -3,14 +3,18 @@ use std::f64::consts::PI;
 
 fn main() -> Result<(), std::io::Error> {
     let mut camera = Camera::new(400, 200, 1.152);
-    camera.transform = view_transform(pt(-2.6, 1.5, -3.9), pt(-0.6, 1.0, -0.8), v(0.0, 1.0, 0.0));
+    camera.set_transform(view_transform(
+        pt(-2.6, 1.5, -3.9),
+        pt(-0.6, 1.0, -0.8),
+        v(0.0, 1.0, 0.0),
+    ));
 
     let light = PointLight::new(pt(-4.9, 4.9, -1.0), Color::new(1.0, 1.0, 1.0));
 
     // let mut wall_material = Material::new();
     // let mut wall_pattern =
     //     stripe_pattern(Color::new(0.45, 0.45, 0.45), Color::new(0.55, 0.55, 0.55));
-    // wall_pattern.transform = Matrix4x4::scaling(0.25, 0.25, 0.25) * Matrix4x4::rotation_y(1.5708);
+    // wall_pattern.set_transform(Matrix4x4::scaling(0.25, 0.25, 0.25) * Matrix4x4::rotation_y(1.5708));
     // wall_material.pattern = Some(wall_pattern);
     // wall_material.ambient = 0.0;
     // wall_material.diffuse = 0.4;
@@ -76,7 +80,7 @@ fn main() -> Result<(), std::io::Error> {
     let cylinder = Cylinder::new(-2.0, 2.0, true).material(Material::new().rgb(0.0, 1.0, 0.0));
     let csg = Csg::difference(cube.shape(), cylinder.shape()).shape();
     let mut world = World::empty();
-    world.lights.push(light);
+    world.lights.push(light.into());
     world.objects = vec![
         // floor.into(),
         // ceiling.into(),
@@ -65,14 +65,17 @@ fn main() -> Result<(), std::io::Error> {
     let left = Sphere::new().transform(transform).material(material);
 
     let mut world = World::empty();
-    world.lights.push(PointLight::new(
-        pt(-10.0, 10.0, -10.0),
-        Color::new(1.0, 1.0, 1.0),
-    ));
+    world
+        .lights
+        .push(PointLight::new(pt(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
     world.objects = vec![floor.into(), middle.into(), left.into(), right.into()];
 
     let mut camera = Camera::new(400, 200, PI / 3.0);
-    camera.transform = view_transform(pt(0.0, 1.5, -5.0), pt(0.0, 1.0, 0.0), v(0.0, 1.0, 0.0));
+    camera.set_transform(view_transform(
+        pt(0.0, 1.5, -5.0),
+        pt(0.0, 1.0, 0.0),
+        v(0.0, 1.0, 0.0),
+    ));
 
     let canvas = camera.render(&mut world);
     std::fs::write("./first_scene.ppm", canvas.to_ppm())
@@ -40,9 +40,13 @@ fn hexagon() -> Box<dyn Shape> {
 fn main() -> Result<(), std::io::Error> {
     let light = PointLight::new(pt(10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
     let mut camera = Camera::new(400, 200, PI / 2.0);
-    camera.transform = view_transform(pt(0.0, 0.0, -5.0), pt(0.0, 0.0, 0.0), v(0.0, 1.0, 0.0));
+    camera.set_transform(view_transform(
+        pt(0.0, 0.0, -5.0),
+        pt(0.0, 0.0, 0.0),
+        v(0.0, 1.0, 0.0),
+    ));
     let mut world = World::empty();
-    world.lights.push(light);
+    world.lights.push(light.into());
     let mut hex = hexagon();
     hex.set_transform(Matrix4x4::rotation_x(-PI / 2.0));
     // materials on groups currently have no effect. :(
@@ -3,14 +3,19 @@ use ray_tracer::*;
 
 fn main() -> Result<()> {
     let mut camera = Camera::new(400, 200, 1.152);
-    camera.transform = view_transform(pt(-2.6, 1.5, -3.9), pt(-0.6, 1.0, -0.8), v(0.0, 1.0, 0.0));
+    camera.set_transform(view_transform(
+        pt(-2.6, 1.5, -3.9),
+        pt(-0.6, 1.0, -0.8),
+        v(0.0, 1.0, 0.0),
+    ));
 
     let light = PointLight::new(pt(-4.9, 4.9, -1.0), Color::new(1.0, 1.0, 1.0));
 
     let mut wall_material = Material::new();
     let mut wall_pattern =
         stripe_pattern(Color::new(0.45, 0.45, 0.45), Color::new(0.55, 0.55, 0.55));
-    wall_pattern.transform = Matrix4x4::scaling(0.25, 0.25, 0.25) * Matrix4x4::rotation_y(1.5708);
+    wall_pattern
+        .set_transform(Matrix4x4::scaling(0.25, 0.25, 0.25) * Matrix4x4::rotation_y(1.5708));
     wall_material.pattern = Some(wall_pattern);
     wall_material.ambient = 0.0;
     wall_material.diffuse = 0.4;
@@ -43,7 +48,7 @@ fn main() -> Result<()> {
                 .rotate_z(1.5708)
                 .translate(-5.0, 0.0, 0.0),
         )
-        .material(wall_material);
+        .material(wall_material.clone());
     let east_wall = Plane::new()
         .transform(
             // Matrix4x4::rotation_y(1.5708),
@@ -52,14 +57,14 @@ fn main() -> Result<()> {
                 .rotate_z(1.5708)
                 .translate(5.0, 0.0, 0.0),
         )
-        .material(wall_material);
+        .material(wall_material.clone());
     let north_wall = Plane::new()
         .transform(
             Matrix4x4::identity()
                 .rotate_x(1.5708)
                 .translate(0.0, 0.0, 5.0),
         )
-        .material(wall_material);
+        .material(wall_material.clone());
     let south_wall = Plane::new()
         .transform(
             Matrix4x4::identity()
@@ -122,7 +127,7 @@ fn main() -> Result<()> {
         );
 
     let mut world = World::empty();
-    world.lights.push(light);
+    world.lights.push(light.into());
     world.objects = vec![
         floor.into(),
         ceiling.into(),
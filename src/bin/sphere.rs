@@ -28,7 +28,7 @@ fn main() -> Result<(), std::io::Error> {
     let shape = Sphere::new().transform(transform).material(material);
     let light_position = Tuple::point(-10.0, 10.0, -10.0);
     let light_color = Color::new(1.0, 1.0, 1.0);
-    let light = PointLight::new(light_position, light_color);
+    let light = PointLight::new(light_position, light_color).into();
     // for each row of pixels in the canvas
     for y in 0..canvas_pixels {
         // compute the world y coordinate (top = +half, bottom = -half)
@@ -47,16 +47,16 @@ fn main() -> Result<(), std::io::Error> {
 
             if let Some(hit) = xs.hit() {
                 let point = r.position(hit.t);
-                let normal = hit.object.normal_at(point, &hit);
+                let normal = hit.object.normal_at(point, hit);
                 let eye = -r.direction;
                 let color = lighting(
-                    &hit.object.material(),
+                    hit.object.material(),
                     hit.object,
                     &light,
                     &point,
                     &eye,
                     &normal,
-                    false,
+                    1.0,
                 );
                 canvas.write_pixel(x, y, color);
             }
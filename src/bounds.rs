@@ -0,0 +1,333 @@
+use crate::{pt, Matrix4x4, Ray, Tuple, EPSILON};
+
+/// An axis-aligned bounding box.
+/// An empty box has its minimum at +infinity and its maximum at -infinity so
+/// that adding any point to it produces a box containing just that point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Bounds {
+    pub fn new(min: Tuple, max: Tuple) -> Bounds {
+        Bounds { min, max }
+    }
+
+    pub fn empty() -> Bounds {
+        Bounds {
+            min: pt(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: pt(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn infinite() -> Bounds {
+        Bounds {
+            min: pt(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: pt(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.x.is_finite()
+            && self.min.y.is_finite()
+            && self.min.z.is_finite()
+            && self.max.x.is_finite()
+            && self.max.y.is_finite()
+            && self.max.z.is_finite()
+    }
+
+    pub fn add_point(&mut self, point: Tuple) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.min.z = self.min.z.min(point.z);
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+        self.max.z = self.max.z.max(point.z);
+    }
+
+    pub fn add_bounds(&mut self, other: &Bounds) {
+        if other.is_empty() {
+            return;
+        }
+        self.add_point(other.min);
+        self.add_point(other.max);
+    }
+
+    pub fn contains_point(&self, point: Tuple) -> bool {
+        self.min.x <= point.x
+            && point.x <= self.max.x
+            && self.min.y <= point.y
+            && point.y <= self.max.y
+            && self.min.z <= point.z
+            && point.z <= self.max.z
+    }
+
+    pub fn contains_bounds(&self, other: &Bounds) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    /// Transforms all eight corners of the box and returns a new axis-aligned
+    /// box that contains them. Boxes that extend to infinity stay infinite in
+    /// every direction since rotating them would otherwise produce NaNs.
+    pub fn transform(&self, transform: &Matrix4x4) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return Bounds::infinite();
+        }
+        let corners = [
+            self.min,
+            pt(self.min.x, self.min.y, self.max.z),
+            pt(self.min.x, self.max.y, self.min.z),
+            pt(self.min.x, self.max.y, self.max.z),
+            pt(self.max.x, self.min.y, self.min.z),
+            pt(self.max.x, self.min.y, self.max.z),
+            pt(self.max.x, self.max.y, self.min.z),
+            self.max,
+        ];
+        let mut bounds = Bounds::empty();
+        for corner in corners.iter() {
+            bounds.add_point(*transform * *corner);
+        }
+        bounds
+    }
+
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        tmin <= tmax
+    }
+
+    /// Splits the box in half along its largest dimension.
+    pub fn split(&self) -> (Bounds, Bounds) {
+        let dx = self.max.x - self.min.x;
+        let dy = self.max.y - self.min.y;
+        let dz = self.max.z - self.min.z;
+        let greatest = dx.max(dy).max(dz);
+
+        let (mut x0, mut y0, mut z0) = (self.min.x, self.min.y, self.min.z);
+        let (mut x1, mut y1, mut z1) = (self.max.x, self.max.y, self.max.z);
+
+        if greatest == dx {
+            x0 += dx / 2.0;
+            x1 = x0;
+        } else if greatest == dy {
+            y0 += dy / 2.0;
+            y1 = y0;
+        } else {
+            z0 += dz / 2.0;
+            z1 = z0;
+        }
+
+        let mid_min = pt(x0, y0, z0);
+        let mid_max = pt(x1, y1, z1);
+        (
+            Bounds::new(self.min, mid_max),
+            Bounds::new(mid_min, self.max),
+        )
+    }
+}
+
+impl Default for Bounds {
+    fn default() -> Bounds {
+        Bounds::empty()
+    }
+}
+
+fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+    let tmin_numerator = min - origin;
+    let tmax_numerator = max - origin;
+
+    let (mut tmin, mut tmax) = if direction.abs() >= EPSILON {
+        (tmin_numerator / direction, tmax_numerator / direction)
+    } else {
+        (
+            tmin_numerator * f64::INFINITY,
+            tmax_numerator * f64::INFINITY,
+        )
+    };
+
+    if tmin > tmax {
+        std::mem::swap(&mut tmin, &mut tmax);
+    }
+
+    (tmin, tmax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn bounds_create() {
+        // creating an empty bounding box
+        let b = Bounds::empty();
+        assert!(b.is_empty());
+        assert_eq!(b.min.x, f64::INFINITY);
+        assert_eq!(b.max.x, f64::NEG_INFINITY);
+
+        // creating a bounding box with volume
+        let b = Bounds::new(pt(-1.0, -2.0, -3.0), pt(3.0, 2.0, 1.0));
+        assert_eq!(b.min, pt(-1.0, -2.0, -3.0));
+        assert_eq!(b.max, pt(3.0, 2.0, 1.0));
+
+        // adding points to an empty bounding box
+        let mut b = Bounds::empty();
+        b.add_point(pt(-5.0, 2.0, 0.0));
+        b.add_point(pt(7.0, 0.0, -3.0));
+        assert_eq!(b.min, pt(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, pt(7.0, 2.0, 0.0));
+
+        // adding one bounding box to another
+        let mut b1 = Bounds::new(pt(-5.0, -2.0, 0.0), pt(7.0, 4.0, 4.0));
+        let b2 = Bounds::new(pt(8.0, -7.0, -2.0), pt(14.0, 2.0, 8.0));
+        b1.add_bounds(&b2);
+        assert_eq!(b1.min, pt(-5.0, -7.0, -2.0));
+        assert_eq!(b1.max, pt(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn bounds_contains() {
+        // checking to see if a box contains a given point
+        let b = Bounds::new(pt(5.0, -2.0, 0.0), pt(11.0, 4.0, 7.0));
+        let scenarios = vec![
+            (pt(5.0, -2.0, 0.0), true),
+            (pt(11.0, 4.0, 7.0), true),
+            (pt(8.0, 1.0, 3.0), true),
+            (pt(3.0, 0.0, 3.0), false),
+            (pt(8.0, -4.0, 3.0), false),
+            (pt(8.0, 1.0, -1.0), false),
+            (pt(13.0, 1.0, 3.0), false),
+            (pt(8.0, 5.0, 3.0), false),
+            (pt(8.0, 1.0, 8.0), false),
+        ];
+        for (point, result) in scenarios {
+            assert_eq!(b.contains_point(point), result, "{:?}", point);
+        }
+
+        // checking to see if a box contains a given box
+        let b = Bounds::new(pt(5.0, -2.0, 0.0), pt(11.0, 4.0, 7.0));
+        let scenarios = vec![
+            (pt(5.0, -2.0, 0.0), pt(11.0, 4.0, 7.0), true),
+            (pt(6.0, -1.0, 1.0), pt(10.0, 3.0, 6.0), true),
+            (pt(4.0, -3.0, -1.0), pt(10.0, 3.0, 6.0), false),
+            (pt(6.0, -1.0, 1.0), pt(12.0, 5.0, 8.0), false),
+        ];
+        for (min, max, result) in scenarios {
+            let other = Bounds::new(min, max);
+            assert_eq!(b.contains_bounds(&other), result, "{:?}", other);
+        }
+    }
+
+    #[test]
+    fn bounds_transform() {
+        // transforming a bounding box
+        let b = Bounds::new(pt(-1.0, -1.0, -1.0), pt(1.0, 1.0, 1.0));
+        let matrix = Matrix4x4::rotation_x(PI / 4.0) * Matrix4x4::rotation_y(PI / 4.0);
+        let b2 = b.transform(&matrix);
+        assert_eq!(b2.min, pt(-2.0_f64.sqrt(), -1.70711, -1.70711));
+        assert_eq!(b2.max, pt(2.0_f64.sqrt(), 1.70711, 1.70711));
+
+        // transforming an infinite bounding box
+        let b = Bounds::new(
+            pt(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            pt(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        let b2 = b.transform(&Matrix4x4::rotation_x(PI / 4.0));
+        assert_eq!(b2.min.y, f64::NEG_INFINITY);
+        assert_eq!(b2.max.y, f64::INFINITY);
+
+        // transforming an empty bounding box
+        let b = Bounds::empty().transform(&Matrix4x4::translation(1.0, 2.0, 3.0));
+        assert!(b.is_empty());
+    }
+
+    #[test]
+    fn bounds_intersects() {
+        // intersecting a ray with a bounding box at the origin
+        let b = Bounds::new(pt(-1.0, -1.0, -1.0), pt(1.0, 1.0, 1.0));
+        let scenarios = vec![
+            (pt(5.0, 0.5, 0.0), v(-1.0, 0.0, 0.0), true),
+            (pt(-5.0, 0.5, 0.0), v(1.0, 0.0, 0.0), true),
+            (pt(0.5, 5.0, 0.0), v(0.0, -1.0, 0.0), true),
+            (pt(0.5, -5.0, 0.0), v(0.0, 1.0, 0.0), true),
+            (pt(0.5, 0.0, 5.0), v(0.0, 0.0, -1.0), true),
+            (pt(0.5, 0.0, -5.0), v(0.0, 0.0, 1.0), true),
+            (pt(0.0, 0.5, 0.0), v(0.0, 0.0, 1.0), true),
+            (pt(-2.0, 0.0, 0.0), v(2.0, 4.0, 6.0), false),
+            (pt(0.0, -2.0, 0.0), v(6.0, 2.0, 4.0), false),
+            (pt(0.0, 0.0, -2.0), v(4.0, 6.0, 2.0), false),
+            (pt(2.0, 0.0, 2.0), v(0.0, 0.0, -1.0), false),
+            (pt(0.0, 2.0, 2.0), v(0.0, -1.0, 0.0), false),
+            (pt(2.0, 2.0, 0.0), v(-1.0, 0.0, 0.0), false),
+        ];
+        for (origin, direction, result) in scenarios {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(b.intersects(&r), result, "{:?}", r);
+        }
+
+        // intersecting a ray with a non-cubic bounding box
+        let b = Bounds::new(pt(5.0, -2.0, 0.0), pt(11.0, 4.0, 7.0));
+        let scenarios = vec![
+            (pt(15.0, 1.0, 2.0), v(-1.0, 0.0, 0.0), true),
+            (pt(-5.0, -1.0, 4.0), v(1.0, 0.0, 0.0), true),
+            (pt(7.0, 6.0, 5.0), v(0.0, -1.0, 0.0), true),
+            (pt(9.0, -5.0, 6.0), v(0.0, 1.0, 0.0), true),
+            (pt(8.0, 2.0, 12.0), v(0.0, 0.0, -1.0), true),
+            (pt(6.0, 0.0, -5.0), v(0.0, 0.0, 1.0), true),
+            (pt(8.0, 1.0, 3.5), v(0.0, 0.0, 1.0), true),
+            (pt(9.0, -1.0, -8.0), v(2.0, 4.0, 6.0), false),
+            (pt(8.0, 3.0, -4.0), v(6.0, 2.0, 4.0), false),
+            (pt(9.0, -1.0, -2.0), v(4.0, 6.0, 2.0), false),
+            (pt(4.0, 0.0, 9.0), v(0.0, 0.0, -1.0), false),
+            (pt(8.0, 6.0, -1.0), v(0.0, -1.0, 0.0), false),
+            (pt(12.0, 5.0, 4.0), v(-1.0, 0.0, 0.0), false),
+        ];
+        for (origin, direction, result) in scenarios {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(b.intersects(&r), result, "{:?}", r);
+        }
+    }
+
+    #[test]
+    fn bounds_split() {
+        // splitting a perfect cube
+        let b = Bounds::new(pt(-1.0, -4.0, -5.0), pt(9.0, 6.0, 5.0));
+        let (left, right) = b.split();
+        assert_eq!(left.min, pt(-1.0, -4.0, -5.0));
+        assert_eq!(left.max, pt(4.0, 6.0, 5.0));
+        assert_eq!(right.min, pt(4.0, -4.0, -5.0));
+        assert_eq!(right.max, pt(9.0, 6.0, 5.0));
+
+        // splitting an x-wide box
+        let b = Bounds::new(pt(-1.0, -2.0, -3.0), pt(9.0, 5.5, 3.0));
+        let (left, right) = b.split();
+        assert_eq!(left.max, pt(4.0, 5.5, 3.0));
+        assert_eq!(right.min, pt(4.0, -2.0, -3.0));
+
+        // splitting a y-wide box
+        let b = Bounds::new(pt(-1.0, -2.0, -3.0), pt(5.0, 8.0, 3.0));
+        let (left, right) = b.split();
+        assert_eq!(left.max, pt(5.0, 3.0, 3.0));
+        assert_eq!(right.min, pt(-1.0, 3.0, -3.0));
+
+        // splitting a z-wide box
+        let b = Bounds::new(pt(-1.0, -2.0, -3.0), pt(5.0, 3.0, 7.0));
+        let (left, right) = b.split();
+        assert_eq!(left.max, pt(5.0, 3.0, 2.0));
+        assert_eq!(right.min, pt(-1.0, -2.0, 2.0));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{pt, Matrix4x4, Noise, Shape, Tuple, UvMapping, UvPattern};
+use std::f64::consts::PI;
+
+// how far apart the heights are looked up to find which way the surface
+// slopes
+const STEP: f64 = 0.0001;
+
+/// The shape of the bumps on a surface, as a height at each point.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BumpDesign {
+    // waves running along x, a unit apart and this high
+    Sine(f64),
+    // ripples as high as the noise's scale
+    Noise(Noise),
+    // the brightness of a 2D pattern wrapped around the shape, with white
+    // this high and black flat
+    HeightMap(UvMapping, UvPattern, f64),
+}
+
+/// Bumps tilt the normal of a surface as if it were rough or rippled,
+/// without changing the shape itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bump {
+    pub design: BumpDesign,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl Bump {
+    pub fn new(design: BumpDesign) -> Bump {
+        Bump {
+            design,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4x4) {
+        self.transform = transform;
+        self.inverse = transform.inverse().unwrap();
+    }
+
+    pub fn height_at(&self, point: Tuple) -> f64 {
+        match &self.design {
+            BumpDesign::Sine(amplitude) => amplitude * (2.0 * PI * point.x).sin(),
+            BumpDesign::Noise(noise) => noise.scale * noise.at(point),
+            BumpDesign::HeightMap(mapping, uv_pattern, depth) => {
+                let (u, v) = mapping.map(point);
+                let color = uv_pattern.uv_pattern_at(u, v);
+                depth * (color.red + color.green + color.blue) / 3.0
+            }
+        }
+    }
+
+    /// Tilts the world space `normal` at `world_point` on `object` away from
+    /// where the bumps rise.
+    pub fn perturb_normal(
+        &self,
+        object: &dyn Shape,
+        world_point: Tuple,
+        normal: Tuple,
+        time: f64,
+    ) -> Tuple {
+        let to_bump = self.inverse * object.common().world_to_object_at(time);
+        let p = to_bump * world_point;
+        let slope = |dx: f64, dy: f64, dz: f64| {
+            let ahead = self.height_at(pt(p.x + dx, p.y + dy, p.z + dz));
+            let behind = self.height_at(pt(p.x - dx, p.y - dy, p.z - dz));
+            (ahead - behind) / (2.0 * STEP)
+        };
+        let gradient = Tuple::vector(
+            slope(STEP, 0.0, 0.0),
+            slope(0.0, STEP, 0.0),
+            slope(0.0, 0.0, STEP),
+        );
+
+        // the gradient changes space the same way a normal does
+        let mut gradient = to_bump.transpose() * gradient;
+        gradient.w = 0.0;
+
+        // only the part of the slope along the surface tilts the normal
+        let along_surface = gradient - normal * gradient.dot(&normal);
+        (normal - along_surface).normalize()
+    }
+}
+
+pub fn sine_bump(amplitude: f64) -> Bump {
+    Bump::new(BumpDesign::Sine(amplitude))
+}
+
+pub fn noise_bump(noise: Noise) -> Bump {
+    Bump::new(BumpDesign::Noise(noise))
+}
+
+pub fn height_map(mapping: UvMapping, uv_pattern: UvPattern, depth: f64) -> Bump {
+    Bump::new(BumpDesign::HeightMap(mapping, uv_pattern, depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{black, v, white, Canvas, Plane, Sphere, TextureFilter, TextureWrap, UvImage};
+
+    fn assert_near(a: Tuple, b: Tuple) {
+        assert!((a - b).magnitude() < 0.0001, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn bump_sine() {
+        let plane = Plane::new();
+        let up = v(0.0, 1.0, 0.0);
+
+        // flat waves leave the normal alone
+        let bump = sine_bump(0.0);
+        assert_eq!(bump.perturb_normal(&plane, pt(0.3, 0.0, 0.7), up, 0.0), up);
+
+        // so does the top of a wave
+        let bump = sine_bump(0.1);
+        assert_near(bump.perturb_normal(&plane, pt(0.25, 0.0, 3.0), up, 0.0), up);
+
+        // where the wave rises the normal leans back
+        let slope = 0.1 * 2.0 * PI;
+        let expected = v(-slope, 1.0, 0.0).normalize();
+        assert_near(
+            bump.perturb_normal(&plane, pt(0.0, 0.0, 0.0), up, 0.0),
+            expected,
+        );
+        let expected = v(slope, 1.0, 0.0).normalize();
+        assert_near(
+            bump.perturb_normal(&plane, pt(0.5, 0.0, 0.0), up, 0.0),
+            expected,
+        );
+
+        // stretching the bumps makes them gentler
+        let mut bump = sine_bump(0.1);
+        bump.set_transform(Matrix4x4::scaling(2.0, 1.0, 1.0));
+        let expected = v(-slope / 2.0, 1.0, 0.0).normalize();
+        assert_near(
+            bump.perturb_normal(&plane, pt(0.0, 0.0, 0.0), up, 0.0),
+            expected,
+        );
+
+        // and they move with the object
+        let plane = Plane::new().transform(Matrix4x4::translation(0.25, 0.0, 0.0));
+        let bump = sine_bump(0.1);
+        let expected = v(-slope, 1.0, 0.0).normalize();
+        assert_near(
+            bump.perturb_normal(&plane, pt(0.25, 0.0, 0.0), up, 0.0),
+            expected,
+        );
+    }
+
+    #[test]
+    fn bump_noise() {
+        let sphere = Sphere::new();
+        let bump = noise_bump(Noise::new(0.5).octaves(3));
+        let mut tilted = false;
+        for i in 0..50 {
+            let theta = i as f64 * 0.3;
+            let point = pt(theta.cos(), 0.0, theta.sin());
+            let normal = v(point.x, point.y, point.z);
+            let bumped = bump.perturb_normal(&sphere, point, normal, 0.0);
+            assert!((bumped.magnitude() - 1.0).abs() < 0.0001);
+            assert!(bumped.dot(&normal) > 0.0);
+            tilted |= (bumped - normal).magnitude() > 0.01;
+        }
+        assert!(tilted);
+
+        // without any scale there are no ripples
+        let bump = noise_bump(Noise::new(0.0));
+        let normal = v(0.0, 0.0, -1.0);
+        assert_eq!(
+            bump.perturb_normal(&sphere, pt(0.0, 0.0, -1.0), normal, 0.0),
+            normal
+        );
+    }
+
+    #[test]
+    fn bump_height_map() {
+        // a height map that is black on the left and white on the right
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(0, 0, black());
+        canvas.write_pixel(1, 0, white());
+        let image = UvImage::new(canvas)
+            .filter(TextureFilter::Bilinear)
+            .wrap(TextureWrap::Clamp);
+        let bump = height_map(UvMapping::Planar, UvPattern::Image(image), 0.1);
+        assert_eq!(bump.height_at(pt(0.1, 0.0, 0.5)), 0.0);
+        assert!((bump.height_at(pt(0.75, 0.0, 0.5)) - 0.1).abs() < 0.0001);
+
+        // the normal leans away from where the map brightens
+        let plane = Plane::new();
+        let up = v(0.0, 1.0, 0.0);
+        let bumped = bump.perturb_normal(&plane, pt(0.5, 0.0, 0.5), up, 0.0);
+        assert_near(bumped, v(-0.2, 1.0, 0.0).normalize());
+
+        // and stays put where the map is flat
+        assert_near(bump.perturb_normal(&plane, pt(0.1, 0.0, 0.5), up, 0.0), up);
+    }
+}
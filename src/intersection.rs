@@ -35,6 +35,7 @@ pub struct Comps<'a> {
     pub under_point: Tuple,
     pub n1: f64,
     pub n2: f64,
+    pub time: f64,
 }
 
 #[derive(Clone, Debug)]
@@ -42,6 +43,8 @@ pub struct Intersection<'a> {
     pub t: f64,
     pub object: &'a dyn Shape,
     pub uv: Option<(f64, f64)>,
+    // the time of the ray that made the intersection
+    pub time: f64,
 }
 
 impl PartialEq for Intersection<'_> {
@@ -56,6 +59,7 @@ impl<'a> Intersection<'a> {
             t,
             object,
             uv: None,
+            time: 0.0,
         }
     }
 
@@ -64,6 +68,7 @@ impl<'a> Intersection<'a> {
             t,
             object,
             uv: Some((u, v)),
+            time: 0.0,
         }
     }
 
@@ -77,10 +82,21 @@ impl<'a> Intersection<'a> {
         let eyev = -ray.direction;
         let normalv = object.normal_at(point, &self);
         let inside = normalv.dot(&eyev) < 0.0;
-        let normalv = if inside { -normalv } else { normalv };
-        let reflectv = ray.direction.reflect(&normalv);
+        // bumps only tilt the normal used for shading, so the points just
+        // above and below the surface still follow its true shape
+        let bumped = match &object.material().bump {
+            Some(bump) => bump.perturb_normal(object, point, normalv, ray.time),
+            None => normalv,
+        };
+        let (normalv, bumped) = if inside {
+            (-normalv, -bumped)
+        } else {
+            (normalv, bumped)
+        };
         let over_point = point + normalv * EPSILON;
         let under_point = point - normalv * EPSILON;
+        let normalv = bumped;
+        let reflectv = ray.direction.reflect(&normalv);
 
         let mut n1 = 1.0;
         let mut n2 = 1.0;
@@ -124,6 +140,7 @@ impl<'a> Intersection<'a> {
             under_point,
             n1,
             n2,
+            time: ray.time,
         }
     }
 }
@@ -149,7 +166,8 @@ impl Intersections for Vec<Intersection<'_>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{equal, pt, v, Matrix4x4, Plane, Sphere};
+    use crate::{equal, pt, sine_bump, v, Matrix4x4, Plane, Sphere};
+    use std::f64::consts::PI;
 
     #[test]
     fn intersection() {
@@ -299,6 +317,28 @@ mod tests {
         assert_eq!(comps.point.z < comps.under_point.z, true);
     }
 
+    #[test]
+    fn comps_bumped_normal() {
+        // bumps tilt the normal, but not the over and under points
+        let mut shape = Plane::new();
+        shape.material_mut().bump = Some(sine_bump(0.1));
+        let r = Ray::new(pt(0.0, 1.0, 0.0), v(0.0, -1.0, 0.0));
+        let i = Intersection::new(1.0, &shape);
+        let comps = i.prepare_computations(&r, &vec![i.clone()]);
+        let normal = v(-0.2 * PI, 1.0, 0.0).normalize();
+        assert!((comps.normalv - normal).magnitude() < 0.0001);
+        assert!((comps.reflectv - r.direction.reflect(&normal)).magnitude() < 0.0001);
+        assert_eq!(comps.over_point, pt(0.0, EPSILON, 0.0));
+        assert_eq!(comps.under_point, pt(0.0, -EPSILON, 0.0));
+
+        // and the tilted normal is flipped along with the true one
+        let r = Ray::new(pt(0.0, -1.0, 0.0), v(0.0, 1.0, 0.0));
+        let comps = i.prepare_computations(&r, &vec![i.clone()]);
+        assert_eq!(comps.inside, true);
+        assert!((comps.normalv + normal).magnitude() < 0.0001);
+        assert_eq!(comps.over_point, pt(0.0, -EPSILON, 0.0));
+    }
+
     #[test]
     fn comps_schlick() {
         // the schlick approximation under total internal reflection
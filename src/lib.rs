@@ -1,27 +1,46 @@
+mod bounds;
+mod bump;
 mod intersection;
+mod light;
 mod material;
 mod matrix;
+mod motion;
+mod noise;
 mod obj;
 mod pattern;
+mod random;
 mod ray;
 mod scene;
+pub mod scene_file;
 mod shape;
 mod shapes;
 mod tuple;
+mod uv;
 
+pub use bounds::Bounds;
+pub use bump::{height_map, noise_bump, sine_bump, Bump, BumpDesign};
 pub use intersection::{schlick, Comps, Intersection, Intersections};
-pub use material::{lighting, m, Material};
+pub use light::{AreaLight, DirectionalLight, Light, PointLight, SpotLight};
+pub use material::{direct_lighting, lighting, m, Material};
 pub use matrix::{id, Matrix2x2, Matrix3x3, Matrix4x4};
+pub use motion::{Motion, Quaternion};
+pub use noise::{perlin, Noise};
 pub use obj::ObjParser;
 pub use pattern::{
-    checkers_pattern, gradient_pattern, ring_pattern, stripe_pattern, stripe_patternt,
-    test_pattern, Pattern,
+    add_pattern, blend_pattern, checkers_pattern, cube_map, gradient_pattern, granite_pattern,
+    marble_pattern, mask_pattern, multiply_pattern, perturbed_pattern, radial_gradient_pattern,
+    ring_pattern, smooth_ring_pattern, solid_pattern, stripe_pattern, stripe_patternt,
+    test_pattern, texture_map, wood_pattern, ColorRamp, Pattern, PatternDesign,
 };
-pub use ray::{PointLight, Ray};
-pub use scene::{view_transform, Camera, World};
+pub use ray::Ray;
+pub use scene::{view_transform, AmbientOcclusion, Camera, Integrator, Sampling, World};
 pub use shape::{Props, Shape};
-pub use shapes::{Cone, Csg, Cube, Cylinder, Group, Normal, Plane, Sphere, Triangle};
+pub use shapes::{Cone, Csg, Cube, Cylinder, Group, Normal, Plane, Sphere, Torus, Triangle};
 pub use tuple::{black, pt, v, white, Canvas, Color, Tuple};
+pub use uv::{
+    cubic_map, cylindrical_map, planar_map, spherical_map, uv_align_check, uv_checkers, uv_image,
+    CubeFace, TextureFilter, TextureWrap, UvImage, UvMapping, UvPattern,
+};
 
 pub const EPSILON: f64 = 0.00001;
 
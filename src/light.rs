@@ -0,0 +1,501 @@
+use crate::random::Rng;
+use crate::{pt, v, Color, Tuple, World};
+use std::f64::consts::PI;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    pub position: Tuple,
+    pub intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Tuple, intensity: Color) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        PointLight {
+            position: pt(0.0, 0.0, 0.0),
+            intensity: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// A rectangular light made up of `usteps` x `vsteps` cells. Shadows are
+/// computed by casting a ray to one point in every cell, which gives them a
+/// soft edge (penumbra).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AreaLight {
+    pub corner: Tuple,
+    // the edges of a single cell
+    pub uvec: Tuple,
+    pub usteps: usize,
+    pub vvec: Tuple,
+    pub vsteps: usize,
+    pub samples: usize,
+    // the center of the light
+    pub position: Tuple,
+    pub intensity: Color,
+    // sample a random point in each cell instead of its center
+    pub jitter: bool,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Tuple,
+        full_uvec: Tuple,
+        usteps: usize,
+        full_vvec: Tuple,
+        vsteps: usize,
+        intensity: Color,
+    ) -> AreaLight {
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as f64,
+            usteps,
+            vvec: full_vvec / vsteps as f64,
+            vsteps,
+            samples: usteps * vsteps,
+            position: corner + full_uvec / 2.0 + full_vvec / 2.0,
+            intensity,
+            jitter: false,
+        }
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> AreaLight {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn point_on_light(&self, u: usize, v: usize, ju: f64, jv: f64) -> Tuple {
+        self.corner + self.uvec * (u as f64 + ju) + self.vvec * (v as f64 + jv)
+    }
+}
+
+/// A point light that only shines in a cone around `direction`. Points
+/// within `inner_angle` of the direction are fully lit, points beyond
+/// `outer_angle` are dark and the light falls off smoothly in between.
+/// Both angles are measured in radians from the center of the cone.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpotLight {
+    pub position: Tuple,
+    pub direction: Tuple,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub intensity: Color,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Tuple,
+        direction: Tuple,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle: outer_angle.max(inner_angle),
+            intensity,
+        }
+    }
+
+    /// How much of the cone's light reaches `point`, ignoring shadows.
+    pub fn falloff(&self, point: Tuple) -> f64 {
+        let cos_angle = (point - self.position).normalize().dot(&self.direction);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_angle >= cos_inner {
+            return 1.0;
+        }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+        // smoothstep between the edges of the cone
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// A light so far away that all of its rays are parallel, like the sun.
+/// `direction` is the way the light travels. A non-zero `angular_diameter`
+/// (in radians) spreads the light over a small disc in the sky, which is
+/// sampled `samples` times to give soft shadows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Tuple,
+    pub intensity: Color,
+    pub angular_diameter: f64,
+    pub samples: usize,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Tuple, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            intensity,
+            angular_diameter: 0.0,
+            samples: 16,
+        }
+    }
+
+    pub fn angular_diameter(mut self, angular_diameter: f64) -> DirectionalLight {
+        self.angular_diameter = angular_diameter;
+        self
+    }
+
+    pub fn samples(mut self, samples: usize) -> DirectionalLight {
+        self.samples = samples.max(1);
+        self
+    }
+
+    // a direction towards a random point on the disc of the sun
+    fn direction_to_light(&self, su: f64, sv: f64) -> Tuple {
+        let axis = -self.direction;
+        // uniformly sample the cone of directions that the disc covers
+        let cos_max = (self.angular_diameter / 2.0).cos();
+        let cos_theta = 1.0 - su * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * sv;
+        let helper = if axis.x.abs() > 0.9 {
+            v(0.0, 1.0, 0.0)
+        } else {
+            v(1.0, 0.0, 0.0)
+        };
+        let tangent = (helper * axis).normalize();
+        let bitangent = axis * tangent;
+        (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + axis * cos_theta)
+            .normalize()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Area(AreaLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    pub fn intensity(&self) -> Color {
+        match self {
+            Light::Point(light) => light.intensity,
+            Light::Area(light) => light.intensity,
+            Light::Spot(light) => light.intensity,
+            Light::Directional(light) => light.intensity,
+        }
+    }
+
+    /// Where the light is. Directional lights have no position, so they
+    /// return the vector pointing towards them instead.
+    pub fn position(&self) -> Tuple {
+        match self {
+            Light::Point(light) => light.position,
+            Light::Area(light) => light.position,
+            Light::Spot(light) => light.position,
+            Light::Directional(light) => -light.direction,
+        }
+    }
+
+    /// The points on the light that are used to illuminate `point`.
+    /// Directional lights are infinitely far away, so their samples are
+    /// vectors pointing towards the light rather than points.
+    /// Jittered samples are seeded from `point` so that the same point is
+    /// always lit the same way.
+    pub fn samples(&self, point: Tuple) -> Vec<Tuple> {
+        match self {
+            Light::Point(light) => vec![light.position],
+            Light::Spot(light) => vec![light.position],
+            Light::Area(light) => {
+                let mut rng = Rng::from_tuple(point);
+                let mut samples = Vec::with_capacity(light.samples);
+                for v in 0..light.vsteps {
+                    for u in 0..light.usteps {
+                        let (ju, jv) = if light.jitter {
+                            (rng.next_f64(), rng.next_f64())
+                        } else {
+                            (0.5, 0.5)
+                        };
+                        samples.push(light.point_on_light(u, v, ju, jv));
+                    }
+                }
+                samples
+            }
+            Light::Directional(light) => {
+                if light.angular_diameter == 0.0 {
+                    return vec![-light.direction];
+                }
+                let mut rng = Rng::from_tuple(point);
+                (0..light.samples)
+                    .map(|_| light.direction_to_light(rng.next_f64(), rng.next_f64()))
+                    .collect()
+            }
+        }
+    }
+
+    /// The fraction of the light that reaches `point` at `time`, from 0.0
+    /// (completely in shadow) to 1.0 (fully lit).
+    pub fn intensity_at(&self, point: Tuple, world: &World, time: f64) -> f64 {
+        if self.falloff(point) == 0.0 {
+            // don't bother casting shadow rays outside of a spotlight's cone
+            return 0.0;
+        }
+        let samples = self.samples(point);
+        let lit = samples
+            .iter()
+            .filter(|sample| !world.is_shadowed(**sample, point, time))
+            .count();
+        lit as f64 / samples.len() as f64
+    }
+
+    /// How much of the light shines towards `point`, ignoring shadows.
+    pub fn falloff(&self, point: Tuple) -> f64 {
+        match self {
+            Light::Spot(light) => light.falloff(point),
+            _ => 1.0,
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Light {
+        Light::Point(light)
+    }
+}
+
+impl From<AreaLight> for Light {
+    fn from(light: AreaLight) -> Light {
+        Light::Area(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Light {
+        Light::Spot(light)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(light: DirectionalLight) -> Light {
+        Light::Directional(light)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equal;
+
+    #[test]
+    fn point_light() {
+        // a point light has a position and an intensity
+        let intensity = Color::new(1.0, 1.0, 1.0);
+        let position = pt(0.0, 0.0, 0.0);
+        let light = PointLight::new(position, intensity);
+        assert_eq!(light.position, position);
+        assert_eq!(light.intensity, intensity);
+    }
+
+    #[test]
+    fn point_light_intensity_at() {
+        // point lights evaluate the light intensity at a given point
+        let w = World::default();
+        let light = w.lights[0];
+        let scenarios = vec![
+            (pt(0.0, 1.0001, 0.0), 1.0),
+            (pt(-1.0001, 0.0, 0.0), 1.0),
+            (pt(0.0, 0.0, -1.0001), 1.0),
+            (pt(0.0, 0.0, 1.0001), 0.0),
+            (pt(1.0001, 0.0, 0.0), 0.0),
+            (pt(0.0, -1.0001, 0.0), 0.0),
+            (pt(0.0, 0.0, 0.0), 0.0),
+        ];
+        for (point, result) in scenarios {
+            assert_eq!(light.intensity_at(point, &w, 0.0), result, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn area_light_create() {
+        // creating an area light
+        let corner = pt(0.0, 0.0, 0.0);
+        let v1 = v(2.0, 0.0, 0.0);
+        let v2 = v(0.0, 0.0, 1.0);
+        let light = AreaLight::new(corner, v1, 4, v2, 2, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(light.corner, corner);
+        assert_eq!(light.uvec, v(0.5, 0.0, 0.0));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, v(0.0, 0.0, 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.samples, 8);
+        assert_eq!(light.position, pt(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn area_light_point_on_light() {
+        // finding a single point on an area light
+        let corner = pt(0.0, 0.0, 0.0);
+        let v1 = v(2.0, 0.0, 0.0);
+        let v2 = v(0.0, 0.0, 1.0);
+        let light = AreaLight::new(corner, v1, 4, v2, 2, Color::new(1.0, 1.0, 1.0));
+        let scenarios = vec![
+            (0, 0, pt(0.25, 0.0, 0.25)),
+            (1, 0, pt(0.75, 0.0, 0.25)),
+            (0, 1, pt(0.25, 0.0, 0.75)),
+            (2, 0, pt(1.25, 0.0, 0.25)),
+            (3, 1, pt(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, result) in scenarios {
+            assert_eq!(light.point_on_light(u, v, 0.5, 0.5), result);
+        }
+
+        // finding a single point on a jittered area light
+        let scenarios = vec![
+            (0, 0, 0.3, 0.7, pt(0.15, 0.0, 0.35)),
+            (1, 0, 0.7, 0.3, pt(0.85, 0.0, 0.15)),
+            (0, 1, 0.3, 0.7, pt(0.15, 0.0, 0.85)),
+            (2, 0, 0.7, 0.3, pt(1.35, 0.0, 0.15)),
+            (3, 1, 0.3, 0.7, pt(1.65, 0.0, 0.85)),
+        ];
+        for (u, v, ju, jv, result) in scenarios {
+            assert_eq!(light.point_on_light(u, v, ju, jv), result);
+        }
+    }
+
+    #[test]
+    fn area_light_intensity_at() {
+        // the area light intensity function
+        let w = World::default();
+        let corner = pt(-0.5, -0.5, -5.0);
+        let v1 = v(1.0, 0.0, 0.0);
+        let v2 = v(0.0, 1.0, 0.0);
+        let light: Light = AreaLight::new(corner, v1, 2, v2, 2, Color::new(1.0, 1.0, 1.0)).into();
+        let scenarios = vec![
+            (pt(0.0, 0.0, 2.0), 0.0),
+            (pt(1.0, -1.0, 2.0), 0.25),
+            (pt(1.5, 0.0, 2.0), 0.5),
+            (pt(1.25, 1.25, 3.0), 0.75),
+            (pt(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (point, result) in scenarios {
+            assert_eq!(light.intensity_at(point, &w, 0.0), result, "{:?}", point);
+        }
+
+        // jittered samples stay within their cells and are repeatable
+        let light = AreaLight::new(corner, v1, 2, v2, 2, Color::new(1.0, 1.0, 1.0)).jitter(true);
+        let light: Light = light.into();
+        let samples = light.samples(pt(1.5, 0.0, 2.0));
+        assert_eq!(samples, light.samples(pt(1.5, 0.0, 2.0)));
+        assert_eq!(samples.len(), 4);
+        for (i, sample) in samples.iter().enumerate() {
+            let (u, v) = ((i % 2) as f64, (i / 2) as f64);
+            assert!(sample.x >= -0.5 + u * 0.5 && sample.x <= u * 0.5);
+            assert!(sample.y >= -0.5 + v * 0.5 && sample.y <= v * 0.5);
+            assert!(equal(sample.z, -5.0));
+        }
+    }
+
+    #[test]
+    fn spot_light_falloff() {
+        // a spotlight is full strength inside its inner cone, dark outside
+        // its outer cone and falls off smoothly in between
+        let light = SpotLight::new(
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 0.0, 2.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.direction, v(0.0, 0.0, 1.0));
+        let scenarios = vec![
+            (pt(0.0, 0.0, 5.0), 1.0),
+            (pt(0.0, 0.1, 1.0), 1.0),
+            (pt(0.0, 1.0, 1.0), 0.0),
+            (pt(2.0, 0.0, 1.0), 0.0),
+            (pt(0.0, 0.0, -5.0), 0.0),
+        ];
+        for (point, result) in scenarios {
+            assert_eq!(light.falloff(point), result, "{:?}", point);
+        }
+        let halfway = (3.0 * PI / 16.0).tan();
+        let falloff = light.falloff(pt(halfway, 0.0, 1.0));
+        assert!(falloff > 0.0 && falloff < 1.0);
+        assert!(light.falloff(pt(halfway * 0.9, 0.0, 1.0)) > falloff);
+    }
+
+    #[test]
+    fn spot_light_intensity_at() {
+        // points outside of the cone are not lit by a spotlight
+        let w = World::default();
+        let light: Light = SpotLight::new(
+            pt(-10.0, 10.0, -10.0),
+            v(1.0, -1.0, 1.0),
+            0.01,
+            0.02,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into();
+        assert_eq!(light.intensity_at(pt(-1.0001, 0.0, 0.0), &w, 0.0), 0.0);
+        assert_eq!(light.intensity_at(pt(0.0, 1.0001, 0.0), &w, 0.0), 0.0);
+        assert_eq!(light.intensity_at(pt(-2.0, 2.0, -2.0), &w, 0.0), 1.0);
+        assert_eq!(
+            light.intensity_at(pt(1.0001, -1.0001, 1.0001), &w, 0.0),
+            0.0
+        );
+    }
+
+    #[test]
+    fn directional_light() {
+        // a directional light shines from the same direction everywhere
+        let w = World::default();
+        let light: Light =
+            DirectionalLight::new(v(1.0, -1.0, 1.0), Color::new(1.0, 1.0, 1.0)).into();
+        let towards = v(-1.0, 1.0, -1.0).normalize();
+        assert_eq!(light.position(), towards);
+        assert_eq!(light.samples(pt(0.0, 100.0, 0.0)), vec![towards]);
+        assert_eq!(light.samples(pt(5.0, -3.0, 2.0)), vec![towards]);
+
+        // shadows are cast no matter how far away the light is
+        let scenarios = vec![
+            (pt(0.0, 10.0, 0.0), 1.0),
+            (pt(10.0, -10.0, 10.0), 0.0),
+            (pt(-20.0, 20.0, -20.0), 1.0),
+            (pt(-2.0, 2.0, -2.0), 1.0),
+            (pt(1000.0, -1000.0, 1000.0), 0.0),
+        ];
+        for (point, result) in scenarios {
+            assert_eq!(light.intensity_at(point, &w, 0.0), result, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn directional_light_angular_diameter() {
+        // a sun with an angular diameter is sampled within its disc
+        let direction = v(0.0, -1.0, 0.0);
+        let light: Light = DirectionalLight::new(direction, Color::new(1.0, 1.0, 1.0))
+            .angular_diameter(0.2)
+            .samples(8)
+            .into();
+        let samples = light.samples(pt(1.0, 0.0, 1.0));
+        assert_eq!(samples, light.samples(pt(1.0, 0.0, 1.0)));
+        assert_eq!(samples.len(), 8);
+        for sample in samples {
+            assert!(sample.is_vector());
+            assert!(equal(sample.magnitude(), 1.0));
+            assert!(sample.dot(&-direction) >= (0.1_f64).cos() - 1e-9);
+        }
+
+        // points near the edge of a shadow are partially lit
+        let w = World::default();
+        let intensity = light.intensity_at(pt(0.99, -1.0, 0.0), &w, 0.0);
+        assert!(intensity > 0.0 && intensity < 1.0, "{}", intensity);
+    }
+}
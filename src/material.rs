@@ -1,10 +1,10 @@
-use crate::{Color, Pattern, PointLight, Shape, Tuple};
+use crate::{black, Bump, Color, Light, Pattern, Shape, Tuple, UvPattern};
 
 pub fn m() -> Material {
     Material::new()
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     pub color: Color,
     pub pattern: Option<Pattern>,
@@ -15,6 +15,12 @@ pub struct Material {
     pub reflective: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    // light given off by the surface itself
+    pub emissive: Color,
+    // bumps that tilt the surface normal
+    pub bump: Option<Bump>,
+    // the tilt of the surface at each texture coordinate of a triangle mesh
+    pub normal_map: Option<UvPattern>,
 }
 
 impl Material {
@@ -29,6 +35,9 @@ impl Material {
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            emissive: black(),
+            bump: None,
+            normal_map: None,
         }
     }
 
@@ -76,43 +85,107 @@ impl Material {
         self.refractive_index = refractive_index;
         self
     }
+
+    pub fn emissive(mut self, emissive: Color) -> Material {
+        self.emissive = emissive;
+        self
+    }
+
+    pub fn bump(mut self, bump: Bump) -> Material {
+        self.bump = Some(bump);
+        self
+    }
+
+    pub fn normal_map(mut self, normal_map: UvPattern) -> Material {
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    /// The color of the surface at `point`, from the pattern if there is one,
+    /// which moves along with `object` as `time` goes by.
+    pub fn color_at(&self, object: &dyn Shape, point: Tuple, time: f64) -> Color {
+        if let Some(pattern) = &self.pattern {
+            pattern.pattern_at_object(object, point, time)
+        } else {
+            self.color
+        }
+    }
 }
 
+/// The light reaching the eye off `object` where it is at time 0.0, the
+/// ambient term included.
 pub fn lighting(
     material: &Material,
     object: &dyn Shape,
-    light: &PointLight,
+    light: &Light,
     point: &Tuple,
     eyev: &Tuple,
     normalv: &Tuple,
-    in_shadow: bool,
+    intensity: f64,
 ) -> Color {
-    let color = if let Some(pattern) = &material.pattern {
-        pattern.pattern_at_object(object, *point)
-    } else {
-        material.color
-    };
-    // combine the surface color with the light's color / intensity
-    let effective_color = color * light.intensity;
+    let color = material.color_at(object, *point, 0.0);
+    // comput the ambient contribution
+    let ambient = color * light.intensity() * material.ambient;
+    let (diffuse, specular) = shade(material, color, light, point, eyev, normalv, intensity);
+    ambient + diffuse + specular
+}
+
+/// The diffuse and specular light reaching the eye from `light` off a
+/// surface of `color`, leaving out the constant ambient term.
+pub fn direct_lighting(
+    material: &Material,
+    color: Color,
+    light: &Light,
+    point: &Tuple,
+    eyev: &Tuple,
+    normalv: &Tuple,
+    intensity: f64,
+) -> Color {
+    let (diffuse, specular) = shade(material, color, light, point, eyev, normalv, intensity);
+    diffuse + specular
+}
 
-    // find the direction to the light source
-    let lightv = (light.position - *point).normalize();
+// the diffuse and specular contributions of the light
+fn shade(
+    material: &Material,
+    color: Color,
+    light: &Light,
+    point: &Tuple,
+    eyev: &Tuple,
+    normalv: &Tuple,
+    intensity: f64,
+) -> (Color, Color) {
+    // combine the surface color with the light's color / intensity
+    let effective_color = color * light.intensity();
 
-    // comput the ambient contribution
-    let ambient = effective_color * material.ambient;
-    if in_shadow {
-        return ambient;
+    // spotlights only reach points inside their cone
+    let intensity = intensity * light.falloff(*point);
+    if intensity == 0.0 {
+        return (black(), black());
     }
 
-    // light_dot_normal represents the cosine of the angle between the
-    // light vector and the normal vector. A negative number means the
-    // light is on the other side of teh surface.
-    let light_dot_normal = lightv.dot(&normalv);
-    let (diffuse, specular) = if light_dot_normal < 0.0 {
-        (Color::new(0.0, 0.0, 0.0), Color::new(0.0, 0.0, 0.0))
-    } else {
+    // average the diffuse and specular contributions of every point sampled
+    // on the light
+    let samples = light.samples(*point);
+    let mut diffuse = Color::new(0.0, 0.0, 0.0);
+    let mut specular = Color::new(0.0, 0.0, 0.0);
+    for sample in &samples {
+        // find the direction to the light source
+        let lightv = if sample.is_vector() {
+            *sample
+        } else {
+            (*sample - *point).normalize()
+        };
+
+        // light_dot_normal represents the cosine of the angle between the
+        // light vector and the normal vector. A negative number means the
+        // light is on the other side of teh surface.
+        let light_dot_normal = lightv.dot(&normalv);
+        if light_dot_normal < 0.0 {
+            continue;
+        }
         // compute the diffuse contribution
-        let diffuse = effective_color * material.diffuse * light_dot_normal;
+        diffuse = diffuse + effective_color * material.diffuse * light_dot_normal;
 
         // reflect-dot_eye represents the cosine of the angle between the
         // reflection vector and the eye vector. A negative number means the
@@ -120,22 +193,23 @@ pub fn lighting(
         let reflectv = -lightv.reflect(&normalv);
         let reflect_dot_eye = reflectv.dot(eyev);
 
-        let specular = if reflect_dot_eye <= 0.0 {
-            Color::new(0.0, 0.0, 0.0)
-        } else {
+        if reflect_dot_eye > 0.0 {
             // compute the specular contribution
             let factor = reflect_dot_eye.powf(material.shininess);
-            light.intensity * material.specular * factor
-        };
-        (diffuse, specular)
-    };
-    ambient + diffuse + specular
+            specular = specular + light.intensity() * material.specular * factor;
+        }
+    }
+    let scale = intensity / samples.len() as f64;
+    (diffuse * scale, specular * scale)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{black, pt, stripe_pattern, v, white, Sphere};
+    use crate::{
+        black, pt, stripe_pattern, v, white, AreaLight, DirectionalLight, PointLight, Sphere,
+        SpotLight,
+    };
 
     #[test]
     fn material() {
@@ -168,20 +242,20 @@ mod tests {
         let result = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &position,
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
 
         // lighting with the surface in shadow
-        let in_shadow = true;
+        let in_shadow = 0.0;
         let result = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &position,
             &eyev,
             &normalv,
@@ -196,11 +270,11 @@ mod tests {
         let result = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &position,
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(1.0, 1.0, 1.0));
 
@@ -210,11 +284,11 @@ mod tests {
         let result = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &position,
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(0.7364, 0.7364, 0.7364));
 
@@ -225,11 +299,11 @@ mod tests {
         let result = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &position,
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364,));
 
@@ -242,11 +316,11 @@ mod tests {
         let result = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &position,
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
@@ -265,22 +339,130 @@ mod tests {
         let c1 = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &pt(0.9, 0.0, 0.0),
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         let c2 = lighting(
             &m,
             &Sphere::new(),
-            &light,
+            &light.into(),
             &pt(1.1, 0.0, 0.0),
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         assert_eq!(c1, white());
         assert_eq!(c2, black());
     }
+
+    #[test]
+    fn lighting_with_light_intensity() {
+        // lighting() uses light intensity to attenuate color
+        let mut m = Material::new();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.0;
+        let light: Light = PointLight::new(pt(0.0, 0.0, -10.0), white()).into();
+        let position = pt(0.0, 0.0, -1.0);
+        let eyev = v(0.0, 0.0, -1.0);
+        let normalv = v(0.0, 0.0, -1.0);
+        let scenarios = vec![
+            (1.0, Color::new(1.0, 1.0, 1.0)),
+            (0.5, Color::new(0.55, 0.55, 0.55)),
+            (0.0, Color::new(0.1, 0.1, 0.1)),
+        ];
+        for (intensity, result) in scenarios {
+            let c = lighting(
+                &m,
+                &Sphere::new(),
+                &light,
+                &position,
+                &eyev,
+                &normalv,
+                intensity,
+            );
+            assert_eq!(c, result);
+        }
+    }
+
+    #[test]
+    fn lighting_with_area_light() {
+        // lighting() samples the area light
+        let corner = pt(-0.5, -0.5, -5.0);
+        let v1 = v(1.0, 0.0, 0.0);
+        let v2 = v(0.0, 1.0, 0.0);
+        let light: Light = AreaLight::new(corner, v1, 2, v2, 2, white()).into();
+        let mut m = Material::new();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.0;
+        let eye = pt(0.0, 0.0, -5.0);
+        let scenarios = vec![
+            (pt(0.0, 0.0, -1.0), Color::new(0.9965, 0.9965, 0.9965)),
+            (
+                pt(0.0, 2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0),
+                Color::new(0.62318, 0.62318, 0.62318),
+            ),
+        ];
+        for (position, result) in scenarios {
+            let eyev = (eye - position).normalize();
+            let normalv = v(position.x, position.y, position.z);
+            let c = lighting(&m, &Sphere::new(), &light, &position, &eyev, &normalv, 1.0);
+            assert_eq!(c, result);
+        }
+    }
+
+    #[test]
+    fn lighting_with_spot_light() {
+        // lighting() only lights points inside a spotlight's cone
+        let light: Light = SpotLight::new(
+            pt(0.0, 0.0, -10.0),
+            v(0.0, 0.0, 1.0),
+            0.1,
+            0.2,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into();
+        let m = Material::new();
+        let eyev = v(0.0, 0.0, -1.0);
+        let normalv = v(0.0, 0.0, -1.0);
+        let sphere = Sphere::new();
+        let c = lighting(
+            &m,
+            &sphere,
+            &light,
+            &pt(0.0, 0.0, 0.0),
+            &eyev,
+            &normalv,
+            1.0,
+        );
+        assert_eq!(c, Color::new(1.9, 1.9, 1.9));
+        let c = lighting(
+            &m,
+            &sphere,
+            &light,
+            &pt(0.0, 5.0, 0.0),
+            &eyev,
+            &normalv,
+            1.0,
+        );
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lighting_with_directional_light() {
+        // lighting() uses the same light vector for every point
+        let light: Light = DirectionalLight::new(v(0.0, 0.0, 1.0), white()).into();
+        let m = Material::new();
+        let eyev = v(0.0, 0.0, -1.0);
+        let normalv = v(0.0, 0.0, -1.0);
+        let sphere = Sphere::new();
+        for position in [pt(0.0, 0.0, 0.0), pt(100.0, -50.0, 1000.0)] {
+            let c = lighting(&m, &sphere, &light, &position, &eyev, &normalv, 1.0);
+            assert_eq!(c, Color::new(1.9, 1.9, 1.9));
+        }
+    }
 }
@@ -182,6 +182,18 @@ impl Matrix4x4 {
         Matrix4x4(ta)
     }
 
+    /// Blends every element of the matrix towards `other`. At `t` = 0.0
+    /// this is `self` and at `t` = 1.0 it is `other`.
+    pub fn lerp(&self, other: &Matrix4x4, t: f64) -> Matrix4x4 {
+        let mut m = self.0;
+        for (row, other_row) in m.iter_mut().zip(other.0.iter()) {
+            for (a, b) in row.iter_mut().zip(other_row.iter()) {
+                *a += (b - *a) * t;
+            }
+        }
+        Matrix4x4(m)
+    }
+
     pub fn submatrix(&self, row: usize, column: usize) -> Matrix3x3 {
         let mut sub = [[0.0; 3]; 3];
         let mut sr = 0;
@@ -788,4 +800,17 @@ mod tests {
             .translate(10.0, 5.0, 7.0);
         assert_eq!(t * p, Tuple::point(15.0, 0.0, 7.0));
     }
+
+    #[test]
+    fn matrix_lerp() {
+        // interpolating between two transformations
+        let a = Matrix4x4::translation(0.0, 0.0, 0.0);
+        let b = Matrix4x4::translation(4.0, -2.0, 0.0) * Matrix4x4::scaling(3.0, 3.0, 3.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(
+            a.lerp(&b, 0.5),
+            Matrix4x4::translation(2.0, -1.0, 0.0) * Matrix4x4::scaling(2.0, 2.0, 2.0)
+        );
+    }
 }
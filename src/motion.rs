@@ -0,0 +1,348 @@
+use crate::{Matrix4x4, Tuple};
+
+// how far what's left of the stretch in a rotation may move before the
+// polar decomposition stops refining it
+const POLAR_TOLERANCE: f64 = 1e-12;
+
+/// A rotation as a unit quaternion, which can be blended with another
+/// without shrinking or skewing whatever it rotates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    /// The rotation in the upper 3x3 of `m`, which must be a rotation.
+    pub fn from_matrix(m: &Matrix4x4) -> Quaternion {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        // work from the largest of w, x, y and z so as not to divide by
+        // something close to zero
+        if trace > 0.0 {
+            let s = 2.0 * (trace + 1.0).sqrt();
+            Quaternion {
+                w: 0.25 * s,
+                x: (m[2][1] - m[1][2]) / s,
+                y: (m[0][2] - m[2][0]) / s,
+                z: (m[1][0] - m[0][1]) / s,
+            }
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0 * (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            Quaternion {
+                w: (m[2][1] - m[1][2]) / s,
+                x: 0.25 * s,
+                y: (m[0][1] + m[1][0]) / s,
+                z: (m[0][2] + m[2][0]) / s,
+            }
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0 * (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            Quaternion {
+                w: (m[0][2] - m[2][0]) / s,
+                x: (m[0][1] + m[1][0]) / s,
+                y: 0.25 * s,
+                z: (m[1][2] + m[2][1]) / s,
+            }
+        } else {
+            let s = 2.0 * (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            Quaternion {
+                w: (m[1][0] - m[0][1]) / s,
+                x: (m[0][2] + m[2][0]) / s,
+                y: (m[1][2] + m[2][1]) / s,
+                z: 0.25 * s,
+            }
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        let Quaternion { w, x, y, z } = *self;
+        Matrix4x4([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn scaled(&self, s: f64) -> Quaternion {
+        Quaternion {
+            w: self.w * s,
+            x: self.x * s,
+            y: self.y * s,
+            z: self.z * s,
+        }
+    }
+
+    fn plus(&self, other: &Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+
+    /// Turns from this rotation towards `other` at a steady rate, the
+    /// shorter way around. At `t` = 0.0 this is `self` and at `t` = 1.0 it
+    /// is `other`.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0.0 {
+            other = other.scaled(-1.0);
+            cos = -cos;
+        }
+        let (a, b) = if cos > 0.9995 {
+            // so close together that blending them directly is as good
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        let q = self.scaled(a).plus(&other.scaled(b));
+        q.scaled(1.0 / q.dot(&q).sqrt())
+    }
+}
+
+// a transform split into the translation, rotation and stretch (any scaling
+// or shearing) that make it up when applied in the reverse of that order
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Decomposed {
+    translation: Tuple,
+    rotation: Quaternion,
+    stretch: Matrix4x4,
+}
+
+impl Decomposed {
+    fn new(m: &Matrix4x4) -> Decomposed {
+        let translation = Tuple::vector(m[0][3], m[1][3], m[2][3]);
+        let mut upper = *m;
+        for row in upper.0.iter_mut().take(3) {
+            row[3] = 0.0;
+        }
+
+        // the polar decomposition: averaging the matrix with its inverse
+        // transpose over and over leaves the rotation nearest to it
+        let mut rotation = upper;
+        for _ in 0..100 {
+            let next = match inverse3(&rotation.transpose()) {
+                Some(inverse_transpose) => rotation.lerp(&inverse_transpose, 0.5),
+                None => break,
+            };
+            let change = (0..3)
+                .flat_map(|r| (0..3).map(move |c| (r, c)))
+                .map(|(r, c)| (next[r][c] - rotation[r][c]).abs())
+                .fold(0.0, f64::max);
+            rotation = next;
+            if change < POLAR_TOLERANCE {
+                break;
+            }
+        }
+
+        // a mirror image is a rotation followed by a negative stretch
+        if determinant3(&rotation) < 0.0 {
+            for row in rotation.0.iter_mut().take(3) {
+                for value in row.iter_mut().take(3) {
+                    *value = -*value;
+                }
+            }
+        }
+        let stretch = rotation.transpose() * upper;
+        Decomposed {
+            translation,
+            rotation: Quaternion::from_matrix(&rotation),
+            stretch,
+        }
+    }
+}
+
+fn determinant3(m: &Matrix4x4) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+// the inverse of a matrix that only uses its upper 3x3
+fn inverse3(m: &Matrix4x4) -> Option<Matrix4x4> {
+    let determinant = determinant3(m);
+    if determinant.abs() < f64::EPSILON {
+        return None;
+    }
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+        (m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]) / determinant
+    };
+    Some(Matrix4x4([
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+            0.0,
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+            0.0,
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+            0.0,
+        ],
+        [0.0, 0.0, 0.0, 1.0],
+    ]))
+}
+
+/// A transform moving from one matrix to another as time goes from 0.0 to
+/// 1.0. The matrices are split into translation, rotation and stretch once,
+/// and those are blended on their own so that a turning shape keeps its
+/// shape along the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motion {
+    start: Decomposed,
+    end: Decomposed,
+    // the inverses of the stretches at either end, used whenever the stretch
+    // doesn't change or the blend between them can't be inverted
+    start_stretch_inverse: Matrix4x4,
+    end_stretch_inverse: Matrix4x4,
+}
+
+impl Motion {
+    pub fn new(start: &Matrix4x4, end: &Matrix4x4) -> Motion {
+        let start = Decomposed::new(start);
+        let end = Decomposed::new(end);
+        Motion {
+            start_stretch_inverse: inverse3(&start.stretch).unwrap_or_default(),
+            end_stretch_inverse: inverse3(&end.stretch).unwrap_or_default(),
+            start,
+            end,
+        }
+    }
+
+    fn parts_at(&self, t: f64) -> (Tuple, Matrix4x4, Matrix4x4) {
+        let (start, end) = (&self.start, &self.end);
+        let translation = start.translation + (end.translation - start.translation) * t;
+        let rotation = start.rotation.slerp(&end.rotation, t).to_matrix();
+        let stretch = start.stretch.lerp(&end.stretch, t);
+        (translation, rotation, stretch)
+    }
+
+    /// The transform at time `t`.
+    pub fn transform_at(&self, t: f64) -> Matrix4x4 {
+        let (translation, rotation, stretch) = self.parts_at(t);
+        Matrix4x4::translation(translation.x, translation.y, translation.z) * rotation * stretch
+    }
+
+    /// The inverse of the transform at time `t`, put together from the
+    /// inverses of its parts.
+    pub fn inverse_at(&self, t: f64) -> Matrix4x4 {
+        let (translation, rotation, stretch) = self.parts_at(t);
+        let stretch_inverse = if self.start.stretch == self.end.stretch {
+            self.start_stretch_inverse
+        } else {
+            inverse3(&stretch).unwrap_or(if t < 0.5 {
+                self.start_stretch_inverse
+            } else {
+                self.end_stretch_inverse
+            })
+        };
+        stretch_inverse
+            * rotation.transpose()
+            * Matrix4x4::translation(-translation.x, -translation.y, -translation.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pt, v};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn quaternion_rotations() {
+        // a quaternion turns back into the rotation it came from
+        let rotations = vec![
+            Matrix4x4::identity(),
+            Matrix4x4::rotation_x(PI / 3.0),
+            Matrix4x4::rotation_y(-2.5),
+            Matrix4x4::rotation_z(PI),
+            Matrix4x4::rotation_x(0.4) * Matrix4x4::rotation_y(2.0) * Matrix4x4::rotation_z(-1.1),
+        ];
+        for rotation in rotations {
+            assert_eq!(Quaternion::from_matrix(&rotation).to_matrix(), rotation);
+        }
+
+        // slerp turns at a steady rate
+        let a = Quaternion::from_matrix(&Matrix4x4::identity());
+        let b = Quaternion::from_matrix(&Matrix4x4::rotation_y(PI / 2.0));
+        assert_eq!(a.slerp(&b, 0.0).to_matrix(), Matrix4x4::identity());
+        assert_eq!(
+            a.slerp(&b, 0.5).to_matrix(),
+            Matrix4x4::rotation_y(PI / 4.0)
+        );
+        assert_eq!(
+            a.slerp(&b, 1.0).to_matrix(),
+            Matrix4x4::rotation_y(PI / 2.0)
+        );
+    }
+
+    #[test]
+    fn motion_blending() {
+        // the ends of a motion are the matrices it was made from
+        let start = Matrix4x4::translation(1.0, 2.0, 3.0)
+            * Matrix4x4::rotation_x(0.3)
+            * Matrix4x4::scaling(2.0, 1.0, 0.5);
+        let end = Matrix4x4::translation(-1.0, 0.0, 4.0)
+            * Matrix4x4::rotation_z(2.0)
+            * Matrix4x4::shearing(0.5, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let motion = Motion::new(&start, &end);
+        assert_eq!(motion.transform_at(0.0), start);
+        assert_eq!(motion.transform_at(1.0), end);
+        assert_eq!(motion.inverse_at(0.0), start.inverse().unwrap());
+        assert_eq!(motion.inverse_at(1.0), end.inverse().unwrap());
+
+        // in between, the inverse undoes the transform
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            let product = motion.transform_at(t) * motion.inverse_at(t);
+            assert_eq!(product, Matrix4x4::identity());
+        }
+
+        // a half turn passes through a quarter turn, keeping its size
+        let motion = Motion::new(&Matrix4x4::identity(), &Matrix4x4::rotation_y(PI));
+        let halfway = motion.transform_at(0.5);
+        let p = halfway * pt(1.0, 0.0, 0.0);
+        assert!((v(p.x, p.y, p.z).magnitude() - 1.0).abs() < 1e-9);
+        assert!(p.x.abs() < 1e-5);
+        assert_eq!(motion.inverse_at(0.5), halfway.inverse().unwrap());
+
+        // mirror images blend too
+        let start = Matrix4x4::scaling(-1.0, 1.0, 1.0);
+        let end = Matrix4x4::scaling(-2.0, 1.0, 1.0) * Matrix4x4::rotation_x(1.0);
+        let motion = Motion::new(&start, &end);
+        assert_eq!(motion.transform_at(0.0), start);
+        assert_eq!(motion.transform_at(1.0), end);
+    }
+}
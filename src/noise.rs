@@ -0,0 +1,226 @@
+use crate::{pt, Tuple};
+
+// Ken Perlin's reference permutation of the numbers 0 to 255
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn hash(i: usize) -> usize {
+    PERMUTATION[i & 255] as usize
+}
+
+// ease the distance into a cell so that the noise is smooth across cells
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+// the dot product of the offset from a corner of the cell with one of
+// twelve gradient directions, picked by the corner's hash
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+/// Ken Perlin's improved noise at `point`. It varies smoothly between about
+/// -1.0 and 1.0, and is 0.0 wherever x, y and z are all whole numbers.
+pub fn perlin(point: Tuple) -> f64 {
+    // the unit cube containing the point, and where the point is inside it
+    let (xf, yf, zf) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let (x, y, z) = (
+        (xf as i64 & 255) as usize,
+        (yf as i64 & 255) as usize,
+        (zf as i64 & 255) as usize,
+    );
+    let (px, py, pz) = (point.x - xf, point.y - yf, point.z - zf);
+    let (u, v, w) = (fade(px), fade(py), fade(pz));
+
+    // hash the coordinates of the eight corners of the cube
+    let a = hash(x) + y;
+    let aa = hash(a) + z;
+    let ab = hash(a + 1) + z;
+    let b = hash(x + 1) + y;
+    let ba = hash(b) + z;
+    let bb = hash(b + 1) + z;
+
+    // and blend the gradients at each corner
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(hash(aa), px, py, pz),
+                grad(hash(ba), px - 1.0, py, pz),
+            ),
+            lerp(
+                u,
+                grad(hash(ab), px, py - 1.0, pz),
+                grad(hash(bb), px - 1.0, py - 1.0, pz),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(hash(aa + 1), px, py, pz - 1.0),
+                grad(hash(ba + 1), px - 1.0, py, pz - 1.0),
+            ),
+            lerp(
+                u,
+                grad(hash(ab + 1), px, py - 1.0, pz - 1.0),
+                grad(hash(bb + 1), px - 1.0, py - 1.0, pz - 1.0),
+            ),
+        ),
+    )
+}
+
+/// Fractal noise made by adding up `octaves` layers of Perlin noise, each
+/// with twice the frequency of the one before and `persistence` times its
+/// amplitude. `scale` is how far it moves the points it perturbs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Noise {
+    pub scale: f64,
+    pub octaves: usize,
+    pub persistence: f64,
+}
+
+impl Noise {
+    pub fn new(scale: f64) -> Noise {
+        Noise {
+            scale,
+            octaves: 1,
+            persistence: 0.5,
+        }
+    }
+
+    pub fn octaves(mut self, octaves: usize) -> Noise {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn persistence(mut self, persistence: f64) -> Noise {
+        self.persistence = persistence;
+        self
+    }
+
+    /// The noise at `point`, kept between about -1.0 and 1.0 however many
+    /// octaves there are.
+    pub fn at(&self, point: Tuple) -> f64 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut max = 0.0;
+        for _ in 0..self.octaves.max(1) {
+            total += perlin(pt(
+                point.x * frequency,
+                point.y * frequency,
+                point.z * frequency,
+            )) * amplitude;
+            // a negative persistence flips every other octave, which must
+            // not cancel out the total they can reach
+            max += amplitude.abs();
+            amplitude *= self.persistence;
+            frequency *= 2.0;
+        }
+        total / max
+    }
+
+    /// Moves `point` by up to `scale` along each axis.
+    pub fn perturb(&self, point: Tuple) -> Tuple {
+        // sample the noise far apart for each axis so they move independently
+        let dx = self.at(point);
+        let dy = self.at(pt(point.x + 31.4, point.y + 27.1, point.z + 82.8));
+        let dz = self.at(pt(point.x + 57.7, point.y + 21.5, point.z + 66.4));
+        pt(
+            point.x + dx * self.scale,
+            point.y + dy * self.scale,
+            point.z + dz * self.scale,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perlin_noise() {
+        // the noise is zero on the corners of every cell
+        for &(x, y, z) in &[(0.0, 0.0, 0.0), (1.0, 2.0, 3.0), (-4.0, 7.0, -9.0)] {
+            assert_eq!(perlin(pt(x, y, z)), 0.0);
+        }
+
+        // and varies smoothly in between
+        let mut previous = perlin(pt(0.0, 0.3, 0.7));
+        let mut varies = false;
+        for i in 1..=1000 {
+            let n = perlin(pt(i as f64 / 100.0, 0.3, 0.7));
+            assert!((-1.0..=1.0).contains(&n));
+            assert!((n - previous).abs() < 0.05);
+            varies |= n != previous;
+            previous = n;
+        }
+        assert!(varies);
+
+        // it is the same everywhere every time
+        assert_eq!(perlin(pt(1.5, 2.25, -3.75)), perlin(pt(1.5, 2.25, -3.75)));
+    }
+
+    #[test]
+    fn fractal_noise() {
+        let noise = Noise::new(0.5).octaves(4).persistence(0.6);
+        assert_eq!(noise.octaves, 4);
+        assert_eq!(noise.persistence, 0.6);
+        for i in 0..100 {
+            let point = pt(i as f64 * 0.37, i as f64 * 0.11, -(i as f64) * 0.23);
+            assert!(noise.at(point).abs() <= 1.0);
+
+            // perturbed points move by no more than the scale on each axis
+            let moved = noise.perturb(point);
+            assert!((moved.x - point.x).abs() <= 0.5);
+            assert!((moved.y - point.y).abs() <= 0.5);
+            assert!((moved.z - point.z).abs() <= 0.5);
+            assert!(moved.is_point());
+        }
+
+        // flipping every other octave keeps it in range too
+        let noise = Noise::new(0.5).octaves(2).persistence(-1.0);
+        for i in 0..100 {
+            let point = pt(i as f64 * 0.37, i as f64 * 0.11, -(i as f64) * 0.23);
+            assert!(noise.at(point).abs() <= 1.0);
+        }
+
+        // a single octave is plain Perlin noise
+        let point = pt(0.3, 0.6, 0.9);
+        assert_eq!(Noise::new(1.0).at(point), perlin(point));
+
+        // noise with no scale leaves points alone
+        assert_eq!(Noise::new(0.0).perturb(point), point);
+    }
+}
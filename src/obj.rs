@@ -7,6 +7,7 @@ pub struct ObjParser {
     contents: String,
     vertices: Vec<Tuple>,
     normals: Vec<Tuple>,
+    texture_coords: Vec<(f64, f64)>,
     default_group: Group,
     groups: HashMap<String, Group>,
     current_group: Option<String>,
@@ -19,6 +20,7 @@ impl ObjParser {
             contents: contents.to_string(),
             vertices: vec![pt(0.0, 0.0, 0.0)],
             normals: vec![pt(0.0, 0.0, 0.0)],
+            texture_coords: vec![(0.0, 0.0)],
             default_group: Group::new(),
             groups: HashMap::new(),
             current_group: None,
@@ -33,13 +35,16 @@ impl ObjParser {
         for p in exprs {
             let mut is = p.split("/");
             let vi = is.next().unwrap().parse()?;
-            is.next();
+            let ti = match is.next() {
+                Some(ti) if !ti.is_empty() => ti.parse()?,
+                _ => 0,
+            };
             let ni = if let Some(ni) = is.next() {
                 ni.parse()?
             } else {
                 0
             };
-            faces.push((vi, 0, ni));
+            faces.push((vi, ti, ni));
         }
 
         Ok(faces)
@@ -69,11 +74,19 @@ impl ObjParser {
             let n2 = self.normals[idxs[idx].2];
             let p3 = self.vertices[idxs[idx + 1].0];
             let n3 = self.normals[idxs[idx + 1].2];
-            let tri = if idxs[1].2 != 0 {
+            let mut tri = if idxs[1].2 != 0 {
                 Triangle::smooth(p1, p2, p3, n1, n2, n3)
             } else {
                 Triangle::new(p1, p2, p3)
             };
+            // faces whose texture indexes don't all name a vt record are
+            // left without texture coordinates
+            let uv = |i: usize| self.texture_coords.get(i).filter(|_| i != 0).copied();
+            if let (Some(uv1), Some(uv2), Some(uv3)) =
+                (uv(idxs[1].1), uv(idxs[idx].1), uv(idxs[idx + 1].1))
+            {
+                tri = tri.uvs(uv1, uv2, uv3);
+            }
             triangles.push(tri);
         }
         triangles
@@ -108,6 +121,12 @@ impl ObjParser {
                     self.groups.insert(current_group.clone(), Group::new());
                     self.current_group = Some(current_group);
                 }
+                "vt" => {
+                    let ts = Self::parse_f64s(&mut exprs)?;
+                    // a missing v is 0, and any third coordinate is ignored
+                    self.texture_coords
+                        .push((ts[1], ts.get(2).copied().unwrap_or(0.0)));
+                }
                 "vn" => {
                     let ns = Self::parse_f64s(&mut exprs)?;
                     // 1-based index...
@@ -119,6 +138,9 @@ impl ObjParser {
         Ok(())
     }
 
+    /// Collects every group in the file under one group. The triangles have
+    /// no material of their own, so they use whatever material that group is
+    /// given.
     pub fn into_group(self) -> Group {
         let mut group = Group::new();
         let ObjParser {
@@ -138,7 +160,7 @@ impl ObjParser {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{pt, Normal};
+    use crate::{pt, v, Color, Material, Normal, Shape};
 
     #[test]
     fn ignore_gibberish() -> Result<()> {
@@ -259,6 +281,17 @@ mod tests {
 
         let g = parser.into_group();
         assert_eq!(g.children.len(), 3);
+
+        // the triangles inherit the material of the group
+        let mut g = g.material(Material::new().rgb(1.0, 0.0, 0.0));
+        g.refresh_parents();
+        for child in &g.children {
+            let group = child.as_any().downcast_ref::<Group>().unwrap();
+            for triangle in &group.children {
+                assert_eq!(triangle.own_material(), None);
+                assert_eq!(triangle.material().color, Color::new(1.0, 0.0, 0.0));
+            }
+        }
         // assert_eq!(&*g.children[0], &parser.groups["FirstGroup"] as &dyn Shape);
         // assert_eq!(&*g.children[1], &parser.groups["SecondGroup"] as &dyn Shape);
         Ok(())
@@ -312,4 +345,55 @@ mod tests {
         assert_eq!(t2, t1);
         Ok(())
     }
+    #[test]
+    fn texture_coordinate_records() -> Result<()> {
+        let contents = "
+        vt 0.5 1
+        vt 0 0 0
+        vt 1
+        ";
+        let parser = ObjParser::from_str(contents)?;
+        assert_eq!(parser.texture_coords[1], (0.5, 1.0));
+        assert_eq!(parser.texture_coords[2], (0.0, 0.0));
+        assert_eq!(parser.texture_coords[3], (1.0, 0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn faces_with_texture_coordinates() -> Result<()> {
+        let contents = "
+        v 0 1 0
+        v -1 0 0
+        v 1 0 0
+        v 0 -1 0
+
+        vt 0.5 1
+        vt 0 0.5
+        vt 1 0.5
+        vt 0.5 0
+
+        vn 0 0 -1
+
+        f 1/1 2/2 3/3
+        f 3/3/1 2/2/1 4/4/1
+        f 1/1 2/2 3/9
+        ";
+        let parser = ObjParser::from_str(contents)?;
+        let g = parser.default_group;
+        assert_eq!(g.children.len(), 3);
+        let t1 = g.children[0].as_any().downcast_ref::<Triangle>().unwrap();
+        let t2 = g.children[1].as_any().downcast_ref::<Triangle>().unwrap();
+        let t3 = g.children[2].as_any().downcast_ref::<Triangle>().unwrap();
+        assert_eq!(t1.uvs, Some([(0.5, 1.0), (0.0, 0.5), (1.0, 0.5)]));
+        assert_eq!(t1.tangents, Some((v(2.0, 0.0, 0.0), v(0.0, 2.0, 0.0))));
+
+        // smooth triangles get texture coordinates too
+        assert_eq!(t2.uvs, Some([(1.0, 0.5), (0.0, 0.5), (0.5, 0.0)]));
+        assert!(matches!(t2.normal, Normal::Smooth(_, _, _)));
+
+        // unless a vt record is missing
+        assert_eq!(t3.uvs, None);
+        assert_eq!(t3.tangents, None);
+        Ok(())
+    }
 }
@@ -1,134 +1,380 @@
-use crate::{Color, Matrix4x4, Shape, Tuple};
+use crate::{Color, CubeFace, Matrix4x4, Noise, Shape, Tuple, UvMapping, UvPattern};
+use std::f64::consts::PI;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// Colors spread along a line from 0.0 to 1.0, blended between where they
+/// stop. Used by the solid textures to turn a noise value into a color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<(f64, Color)>,
+}
+
+impl ColorRamp {
+    /// A ramp from `(position, color)` pairs, in any order. A stop at NaN
+    /// is sorted to one of the ends.
+    pub fn new(mut stops: Vec<(f64, Color)>) -> ColorRamp {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp { stops }
+    }
+
+    /// A ramp with the colors spaced evenly from 0.0 to 1.0.
+    pub fn even(colors: Vec<Color>) -> ColorRamp {
+        let last = (colors.len() as f64 - 1.0).max(1.0);
+        ColorRamp::new(
+            colors
+                .into_iter()
+                .enumerate()
+                .map(|(i, color)| (i as f64 / last, color))
+                .collect(),
+        )
+    }
+
+    pub fn stops(&self) -> &[(f64, Color)] {
+        &self.stops
+    }
+
+    /// The color at `t`, which is the first or last color beyond the ends.
+    pub fn at(&self, t: f64) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::default(),
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+            if t <= t1 {
+                return a + (b - a) * ((t - t0) / (t1 - t0));
+            }
+        }
+        last.1
+    }
+}
+
+/// What a pattern looks like. Patterns that combine others hold them as
+/// nested patterns, each with its own transformation, so that for example
+/// the bands of a stripe pattern can themselves be checkered.
+#[derive(Debug, Clone, PartialEq)]
 pub enum PatternDesign {
-    Stripe(Color, Color),
-    Gradient(Color, Color),
-    Ring(Color, Color),
-    Checkers(Color, Color),
+    // the same color everywhere
+    Solid(Color),
+    Stripe(Box<Pattern>, Box<Pattern>),
+    Gradient(Box<Pattern>, Box<Pattern>),
+    // a gradient that repeats outwards from the y axis
+    RadialGradient(Box<Pattern>, Box<Pattern>),
+    Ring(Box<Pattern>, Box<Pattern>),
+    // rings that blend smoothly from one pattern to the other and back
+    SmoothRing(Box<Pattern>, Box<Pattern>),
+    Checkers(Box<Pattern>, Box<Pattern>),
+    // the average of two patterns
+    Blend(Box<Pattern>, Box<Pattern>),
+    // the sum of two patterns
+    Add(Box<Pattern>, Box<Pattern>),
+    // the product of two patterns
+    Multiply(Box<Pattern>, Box<Pattern>),
+    // the second pattern where the first (the mask) is brighter than 50%
+    // gray, and the third everywhere else
+    Mask(Box<Pattern>, Box<Pattern>, Box<Pattern>),
+    // a 2D pattern wrapped around the shape
+    Map(UvMapping, UvPattern),
+    // a 2D pattern for each face of a cube, in CubeFace order
+    CubeMap(Box<[UvPattern; 6]>),
+    // another pattern looked up at points jittered by noise
+    Perturbed(Box<Pattern>, Noise),
+    // growth rings around the y axis, bent by the noise
+    Wood(ColorRamp, Noise),
+    // bands along x, twisted into veins by the noise
+    Marble(ColorRamp, Noise),
+    // the noise itself, best with many octaves and a sharp ramp
+    Granite(ColorRamp, Noise),
     Test,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pattern {
     pub design: PatternDesign,
-    pub transform: Matrix4x4,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
 }
 
 impl Pattern {
+    pub fn new(design: PatternDesign) -> Pattern {
+        Pattern {
+            design,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4x4) {
+        self.transform = transform;
+        self.inverse = transform.inverse().unwrap();
+    }
+
     pub fn pattern_at(&self, point: Tuple) -> Color {
-        match self.design {
+        match &self.design {
+            PatternDesign::Solid(color) => *color,
             PatternDesign::Stripe(a, b) => {
                 if point.x.floor() as isize % 2 == 0 {
-                    a
+                    a.nested_at(point)
                 } else {
-                    b
+                    b.nested_at(point)
                 }
             }
             // take the distance between the two colors, multiply by the
             // fractional portion of the x coordinate, and add the product
             // to the first color.
             PatternDesign::Gradient(a, b) => {
-                let distance = b - a;
+                let a = a.nested_at(point);
+                let distance = b.nested_at(point) - a;
                 let fraction = point.x - point.x.floor();
 
                 a + distance * fraction
             }
+            PatternDesign::RadialGradient(a, b) => {
+                let a = a.nested_at(point);
+                let distance = b.nested_at(point) - a;
+                let radius = (point.x * point.x + point.z * point.z).sqrt();
+                let fraction = radius - radius.floor();
+
+                a + distance * fraction
+            }
             PatternDesign::Ring(a, b) => {
                 let x2 = point.x * point.x;
                 let z2 = point.z * point.z;
                 if (x2 + z2).sqrt() as isize % 2 == 0 {
-                    a
+                    a.nested_at(point)
                 } else {
-                    b
+                    b.nested_at(point)
                 }
             }
+            // blend along a cosine so there are no edges between the rings
+            PatternDesign::SmoothRing(a, b) => {
+                let a = a.nested_at(point);
+                let distance = b.nested_at(point) - a;
+                let radius = (point.x * point.x + point.z * point.z).sqrt();
+                let fraction = (1.0 - (radius * PI).cos()) / 2.0;
+
+                a + distance * fraction
+            }
             PatternDesign::Checkers(a, b) => {
                 if (point.x.floor() + point.y.floor() + point.z.floor()) as isize % 2 == 0 {
-                    a
+                    a.nested_at(point)
+                } else {
+                    b.nested_at(point)
+                }
+            }
+            PatternDesign::Blend(a, b) => (a.nested_at(point) + b.nested_at(point)) / 2.0,
+            PatternDesign::Add(a, b) => a.nested_at(point) + b.nested_at(point),
+            PatternDesign::Multiply(a, b) => a.nested_at(point) * b.nested_at(point),
+            PatternDesign::Mask(mask, a, b) => {
+                let mask = mask.nested_at(point);
+                if (mask.red + mask.green + mask.blue) / 3.0 >= 0.5 {
+                    a.nested_at(point)
                 } else {
-                    b
+                    b.nested_at(point)
                 }
             }
+            PatternDesign::Map(mapping, uv_pattern) => {
+                let (u, v) = mapping.map(point);
+                uv_pattern.uv_pattern_at(u, v)
+            }
+            PatternDesign::CubeMap(faces) => {
+                let face = CubeFace::from_point(point);
+                let (u, v) = face.uv(point);
+                faces[face as usize].uv_pattern_at(u, v)
+            }
+            PatternDesign::Perturbed(pattern, noise) => pattern.nested_at(noise.perturb(point)),
+            // the scale of the noise is how far it bends the rings, bands and
+            // speckles out of shape
+            PatternDesign::Wood(ramp, noise) => {
+                let radius = (point.x * point.x + point.z * point.z).sqrt();
+                let grain = radius + noise.at(point) * noise.scale;
+                ramp.at(grain - grain.floor())
+            }
+            PatternDesign::Marble(ramp, noise) => {
+                let vein = (PI * (point.x + noise.at(point) * noise.scale)).sin();
+                ramp.at((1.0 + vein) / 2.0)
+            }
+            PatternDesign::Granite(ramp, noise) => ramp.at(0.5 + noise.at(point) * noise.scale),
             PatternDesign::Test => Color::new(point.x, point.y, point.z),
         }
     }
 
-    pub fn pattern_at_object(&self, object: &dyn Shape, world_point: Tuple) -> Color {
-        let object_point = object.world_to_object(world_point);
-        let pattern_point = self.transform.inverse().unwrap() * object_point;
+    // the color of a pattern nested in another, at a point in the space of
+    // the pattern containing it
+    fn nested_at(&self, point: Tuple) -> Color {
+        match &self.design {
+            // no need to transform the point when the color is everywhere
+            PatternDesign::Solid(color) => *color,
+            _ => self.pattern_at(self.inverse * point),
+        }
+    }
+
+    /// The color at `world_point` on `object`, where the object is at `time`.
+    pub fn pattern_at_object(&self, object: &dyn Shape, world_point: Tuple, time: f64) -> Color {
+        let object_point = object.world_to_object_at(world_point, time);
+        let pattern_point = self.inverse * object_point;
         self.pattern_at(pattern_point)
     }
 }
 
-pub fn stripe_pattern(a: Color, b: Color) -> Pattern {
-    Pattern {
-        design: PatternDesign::Stripe(a, b),
-        transform: Matrix4x4::identity(),
+impl From<Color> for Pattern {
+    fn from(color: Color) -> Pattern {
+        solid_pattern(color)
     }
 }
 
-pub fn stripe_patternt(a: Color, b: Color, transform: Matrix4x4) -> Pattern {
-    Pattern {
-        design: PatternDesign::Stripe(a, b),
-        transform,
-    }
+pub fn solid_pattern(color: Color) -> Pattern {
+    Pattern::new(PatternDesign::Solid(color))
 }
 
-pub fn gradient_pattern(a: Color, b: Color) -> Pattern {
-    Pattern {
-        design: PatternDesign::Gradient(a, b),
-        transform: Matrix4x4::identity(),
-    }
+// the patterns below take either colors or other patterns
+
+pub fn stripe_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Stripe(
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
 }
 
-pub fn ring_pattern(a: Color, b: Color) -> Pattern {
-    Pattern {
-        design: PatternDesign::Ring(a, b),
-        transform: Matrix4x4::identity(),
-    }
+pub fn stripe_patternt(
+    a: impl Into<Pattern>,
+    b: impl Into<Pattern>,
+    transform: Matrix4x4,
+) -> Pattern {
+    let mut pattern = stripe_pattern(a, b);
+    pattern.set_transform(transform);
+    pattern
 }
 
-pub fn checkers_pattern(a: Color, b: Color) -> Pattern {
-    Pattern {
-        design: PatternDesign::Checkers(a, b),
-        transform: Matrix4x4::identity(),
-    }
+pub fn gradient_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Gradient(
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
+}
+
+pub fn radial_gradient_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::RadialGradient(
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
+}
+
+pub fn ring_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Ring(Box::new(a.into()), Box::new(b.into())))
+}
+
+pub fn smooth_ring_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::SmoothRing(
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
+}
+
+pub fn checkers_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Checkers(
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
+}
+
+pub fn blend_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Blend(Box::new(a.into()), Box::new(b.into())))
+}
+
+pub fn add_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Add(Box::new(a.into()), Box::new(b.into())))
+}
+
+pub fn multiply_pattern(a: impl Into<Pattern>, b: impl Into<Pattern>) -> Pattern {
+    Pattern::new(PatternDesign::Multiply(
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
+}
+
+pub fn mask_pattern(
+    mask: impl Into<Pattern>,
+    a: impl Into<Pattern>,
+    b: impl Into<Pattern>,
+) -> Pattern {
+    Pattern::new(PatternDesign::Mask(
+        Box::new(mask.into()),
+        Box::new(a.into()),
+        Box::new(b.into()),
+    ))
+}
+
+pub fn texture_map(mapping: UvMapping, uv_pattern: UvPattern) -> Pattern {
+    Pattern::new(PatternDesign::Map(mapping, uv_pattern))
+}
+
+pub fn cube_map(
+    left: UvPattern,
+    front: UvPattern,
+    right: UvPattern,
+    back: UvPattern,
+    up: UvPattern,
+    down: UvPattern,
+) -> Pattern {
+    Pattern::new(PatternDesign::CubeMap(Box::new([
+        left, front, right, back, up, down,
+    ])))
+}
+
+pub fn perturbed_pattern(pattern: Pattern, noise: Noise) -> Pattern {
+    Pattern::new(PatternDesign::Perturbed(Box::new(pattern), noise))
+}
+
+pub fn wood_pattern(ramp: ColorRamp, noise: Noise) -> Pattern {
+    Pattern::new(PatternDesign::Wood(ramp, noise))
+}
+
+pub fn marble_pattern(ramp: ColorRamp, noise: Noise) -> Pattern {
+    Pattern::new(PatternDesign::Marble(ramp, noise))
+}
+
+pub fn granite_pattern(ramp: ColorRamp, noise: Noise) -> Pattern {
+    Pattern::new(PatternDesign::Granite(ramp, noise))
 }
 
 pub fn test_pattern() -> Pattern {
-    Pattern {
-        design: PatternDesign::Test,
-        transform: Matrix4x4::identity(),
-    }
+    Pattern::new(PatternDesign::Test)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{black, pt, white, Matrix4x4, Sphere};
+    use crate::{black, pt, uv_align_check, uv_checkers, white, Matrix4x4, Sphere};
+    use std::f64::consts::PI;
 
     #[test]
     fn pattern_default() {
         // the default pattern transformation
         let mut pattern = test_pattern();
-        assert_eq!(pattern.transform, Matrix4x4::identity());
+        assert_eq!(pattern.transform(), &Matrix4x4::identity());
 
         // assigning a transformation
-        pattern.transform = Matrix4x4::translation(1.0, 2.0, 3.0);
-        assert_eq!(pattern.transform, Matrix4x4::translation(1.0, 2.0, 3.0));
+        pattern.set_transform(Matrix4x4::translation(1.0, 2.0, 3.0));
+        assert_eq!(pattern.transform(), &Matrix4x4::translation(1.0, 2.0, 3.0));
 
         // a pattern with an object transformation
         let shape = Sphere::new().transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
         let pattern = test_pattern();
-        let c = pattern.pattern_at_object(&shape, pt(2.0, 3.0, 4.0));
+        let c = pattern.pattern_at_object(&shape, pt(2.0, 3.0, 4.0), 0.0);
         assert_eq!(c, Color::new(1.0, 1.5, 2.0));
 
         // a pattern with a pattern transformation
         let shape = Sphere::new().transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
         let mut pattern = test_pattern();
-        pattern.transform = Matrix4x4::translation(0.5, 1.0, 1.5);
-        let c = pattern.pattern_at_object(&shape, pt(2.5, 3.0, 3.5));
+        pattern.set_transform(Matrix4x4::translation(0.5, 1.0, 1.5));
+        let c = pattern.pattern_at_object(&shape, pt(2.5, 3.0, 3.5), 0.0);
         assert_eq!(c, Color::new(0.75, 0.5, 0.25));
     }
 
@@ -164,20 +410,30 @@ mod tests {
         // stripes with an object transformation
         let object = Sphere::new().transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
         let pattern = stripe_pattern(white(), black());
-        let c = pattern.pattern_at_object(&object, pt(1.5, 0.0, 0.0));
+        let c = pattern.pattern_at_object(&object, pt(1.5, 0.0, 0.0), 0.0);
         assert_eq!(c, white());
 
         // stripes with a pattern tranformation
         let object = Sphere::new();
         let pattern = stripe_patternt(white(), black(), Matrix4x4::scaling(2.0, 2.0, 2.0));
-        let c = pattern.pattern_at_object(&object, pt(1.5, 0.0, 0.0));
+        let c = pattern.pattern_at_object(&object, pt(1.5, 0.0, 0.0), 0.0);
         assert_eq!(c, white());
 
         // stripes with both an object and a pattern transformation
         let object = Sphere::new().transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
         let pattern = stripe_patternt(white(), black(), Matrix4x4::scaling(2.0, 2.0, 2.0));
-        let c = pattern.pattern_at_object(&object, pt(2.5, 0.0, 0.0));
+        let c = pattern.pattern_at_object(&object, pt(2.5, 0.0, 0.0), 0.0);
         assert_eq!(c, white());
+
+        // a pattern moves along with its object
+        let object = Sphere::new().transform_end(Matrix4x4::translation(10.0, 0.0, 0.0));
+        let pattern = test_pattern();
+        let c = pattern.pattern_at_object(&object, pt(10.0, 0.0, -1.0), 1.0);
+        assert_eq!(c, Color::new(0.0, 0.0, -1.0));
+        let c = pattern.pattern_at_object(&object, pt(5.0, 0.0, -1.0), 0.5);
+        assert_eq!(c, Color::new(0.0, 0.0, -1.0));
+        let c = pattern.pattern_at_object(&object, pt(0.0, 0.0, -1.0), 0.0);
+        assert_eq!(c, Color::new(0.0, 0.0, -1.0));
     }
 
     #[test]
@@ -199,6 +455,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pattern_radial_gradient() {
+        // a radial gradient blends outwards in both x and z
+        let pattern = radial_gradient_pattern(white(), black());
+        assert_eq!(pattern.pattern_at(pt(0.0, 0.0, 0.0)), white());
+        assert_eq!(
+            pattern.pattern_at(pt(0.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(pt(0.0, 5.0, -0.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(pt(0.6, 0.0, 0.8)), white());
+    }
+
     #[test]
     fn pattern_ring() {
         // a ring should extend in both x and z
@@ -210,6 +482,102 @@ mod tests {
         assert_eq!(pattern.pattern_at(pt(0.708, 0.0, 0.708)), black());
     }
 
+    #[test]
+    fn pattern_smooth_ring() {
+        // a smooth ring blends from one color to the other and back
+        let pattern = smooth_ring_pattern(white(), black());
+        assert_eq!(pattern.pattern_at(pt(0.0, 0.0, 0.0)), white());
+        assert_eq!(
+            pattern.pattern_at(pt(0.5, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(pt(0.0, 3.0, 1.0)), black());
+        assert_eq!(
+            pattern.pattern_at(pt(0.0, 0.0, -1.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(pt(1.2, 0.0, 1.6)), white());
+    }
+
+    #[test]
+    fn pattern_color_ramp() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let ramp = ColorRamp::new(vec![(1.0, white()), (0.0, black()), (0.2, red)]);
+        assert_eq!(ramp.stops()[1], (0.2, red));
+
+        // the ends stretch out beyond the first and last stops
+        assert_eq!(ramp.at(-1.0), black());
+        assert_eq!(ramp.at(0.0), black());
+        assert_eq!(ramp.at(2.0), white());
+
+        // and the colors blend in between
+        assert_eq!(ramp.at(0.1), Color::new(0.5, 0.0, 0.0));
+        assert_eq!(ramp.at(0.2), red);
+        assert_eq!(ramp.at(0.6), Color::new(1.0, 0.5, 0.5));
+
+        // evenly spaced colors
+        let ramp = ColorRamp::even(vec![black(), red, white()]);
+        assert_eq!(ramp.at(0.5), red);
+        assert_eq!(ramp.at(0.25), Color::new(0.5, 0.0, 0.0));
+
+        // a stop that isn't a number is sorted last rather than panicking
+        let ramp = ColorRamp::new(vec![(f64::NAN, white()), (1.0, red), (0.0, black())]);
+        assert_eq!(ramp.stops()[0], (0.0, black()));
+        assert_eq!(ramp.stops()[1], (1.0, red));
+        assert!(ramp.stops()[2].0.is_nan());
+    }
+
+    #[test]
+    fn pattern_solid_textures() {
+        let ramp = ColorRamp::even(vec![black(), white()]);
+        let brightness = |color: Color| color.red;
+
+        // without noise, wood has rings around the y axis
+        let pattern = wood_pattern(ramp.clone(), Noise::new(0.0));
+        assert_eq!(pattern.pattern_at(pt(0.0, 5.0, 0.0)), black());
+        assert_eq!(
+            pattern.pattern_at(pt(0.0, 0.0, 1.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(pt(0.6, 0.0, 0.8)), black());
+
+        // without noise, marble has bands along x
+        let pattern = marble_pattern(ramp.clone(), Noise::new(0.0));
+        assert_eq!(
+            pattern.pattern_at(pt(0.0, 2.0, 3.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(pt(0.5, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.0)), black());
+
+        // without noise, granite is the middle of the ramp
+        let pattern = granite_pattern(ramp.clone(), Noise::new(0.0));
+        assert_eq!(
+            pattern.pattern_at(pt(0.3, 0.7, 0.1)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+
+        // noise breaks each of them up, staying within the ramp
+        let noise = Noise::new(1.0).octaves(4);
+        for pattern in &[
+            wood_pattern(ramp.clone(), noise),
+            marble_pattern(ramp.clone(), noise),
+            granite_pattern(ramp.clone(), noise),
+        ] {
+            let colors: Vec<f64> = (0..100)
+                .map(|i| pt(i as f64 * 0.13, 0.37, i as f64 * 0.07))
+                .map(|point| brightness(pattern.pattern_at(point)))
+                .collect();
+            assert!(colors.iter().all(|c| (0.0..=1.0).contains(c)));
+            assert!(colors.iter().any(|&c| c != colors[0]));
+        }
+        let wood = wood_pattern(ramp.clone(), noise);
+        let plain = wood_pattern(ramp, Noise::new(0.0));
+        assert!((0..100)
+            .map(|i| pt(i as f64 * 0.13, 0.37, 0.5))
+            .any(|point| wood.pattern_at(point) != plain.pattern_at(point)));
+    }
+
     #[test]
     fn pattern_checkers() {
         // checkers should repeat in x
@@ -230,4 +598,181 @@ mod tests {
         assert_eq!(pattern.pattern_at(pt(0.0, 0.0, 0.99)), white());
         assert_eq!(pattern.pattern_at(pt(0.0, 0.0, 1.01)), black());
     }
+
+    #[test]
+    fn pattern_perturbed() {
+        // without any noise the pattern is unchanged
+        let stripes = stripe_pattern(white(), black());
+        let pattern = perturbed_pattern(stripes.clone(), Noise::new(0.0));
+        for i in 0..20 {
+            let point = pt(i as f64 * 0.3, 0.5, 0.5);
+            assert_eq!(pattern.pattern_at(point), stripes.pattern_at(point));
+        }
+
+        // noise moves the edges of the stripes
+        let pattern = perturbed_pattern(stripes.clone(), Noise::new(0.5).octaves(3));
+        let differs = (0..100)
+            .map(|i| pt(i as f64 * 0.13, 0.5, 0.5))
+            .any(|point| pattern.pattern_at(point) != stripes.pattern_at(point));
+        assert!(differs);
+
+        // the inner pattern's transformation still applies
+        let inner = stripe_patternt(white(), black(), Matrix4x4::scaling(2.0, 2.0, 2.0));
+        let pattern = perturbed_pattern(inner, Noise::new(0.0));
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.0)), white());
+    }
+
+    #[test]
+    fn pattern_nested() {
+        // stripes whose bands are checkers, each with its own transformation
+        let checkers = checkers_pattern(white(), black());
+        let mut small_checkers = checkers.clone();
+        small_checkers.set_transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
+        let pattern = stripe_pattern(checkers, small_checkers);
+        assert_eq!(pattern.pattern_at(pt(0.5, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(pt(0.5, 0.0, 1.5)), black());
+        assert_eq!(pattern.pattern_at(pt(1.25, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(pt(1.75, 0.0, 0.0)), black());
+
+        // colors and patterns can be mixed
+        let pattern = ring_pattern(Color::new(1.0, 0.0, 0.0), stripe_pattern(white(), black()));
+        assert_eq!(
+            pattern.pattern_at(pt(0.5, 0.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(pt(0.0, 0.0, 1.5)), white());
+
+        // a gradient between two patterns
+        let pattern = gradient_pattern(stripe_pattern(white(), black()), black());
+        assert_eq!(
+            pattern.pattern_at(pt(0.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+
+        // a solid pattern is the same color everywhere
+        let pattern = solid_pattern(Color::new(0.1, 0.2, 0.3));
+        assert_eq!(pattern, Color::new(0.1, 0.2, 0.3).into());
+        assert_eq!(
+            pattern.pattern_at(pt(7.0, -3.0, 0.5)),
+            Color::new(0.1, 0.2, 0.3)
+        );
+    }
+
+    #[test]
+    fn pattern_combinators() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let stripes = stripe_pattern(white(), black());
+        let mut crossing = stripes.clone();
+        crossing.set_transform(Matrix4x4::rotation_y(PI / 2.0));
+
+        // blending averages two patterns
+        let pattern = blend_pattern(stripes.clone(), crossing.clone());
+        assert_eq!(pattern.pattern_at(pt(0.5, 0.0, -0.5)), white());
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, -0.5)), gray);
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.5)), black());
+
+        // adding and multiplying two patterns
+        let pattern = add_pattern(stripes.clone(), red);
+        assert_eq!(
+            pattern.pattern_at(pt(0.5, 0.0, 0.0)),
+            Color::new(2.0, 1.0, 1.0)
+        );
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.0)), red);
+        let pattern = multiply_pattern(stripes.clone(), red);
+        assert_eq!(pattern.pattern_at(pt(0.5, 0.0, 0.0)), red);
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.0)), black());
+
+        // a mask picks between two patterns
+        let pattern = mask_pattern(stripes.clone(), red, checkers_pattern(white(), black()));
+        assert_eq!(pattern.pattern_at(pt(0.5, 0.0, 0.0)), red);
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(pt(1.5, 0.0, 1.5)), white());
+        let pattern = mask_pattern(gray, red, black());
+        assert_eq!(pattern.pattern_at(pt(0.0, 0.0, 0.0)), red);
+    }
+
+    #[test]
+    fn pattern_texture_map() {
+        // using a texture map pattern with a spherical map
+        let checkers = uv_checkers(16.0, 8.0, black(), white());
+        let pattern = texture_map(UvMapping::Spherical, checkers);
+        let scenarios = vec![
+            (pt(0.4315, 0.4670, 0.7719), white()),
+            (pt(-0.9654, 0.2552, -0.0534), black()),
+            (pt(0.1039, 0.7090, 0.6975), white()),
+            (pt(-0.4986, -0.7856, -0.3663), black()),
+            (pt(-0.0317, -0.9395, 0.3411), black()),
+            (pt(0.4809, -0.7721, 0.4154), black()),
+            (pt(0.0285, -0.9612, -0.2745), black()),
+            (pt(-0.5734, -0.2162, -0.7903), white()),
+            (pt(0.7688, -0.1470, 0.6223), black()),
+            (pt(-0.7652, 0.2175, 0.6060), black()),
+        ];
+        for (point, color) in scenarios {
+            assert_eq!(pattern.pattern_at(point), color, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn pattern_cube_map() {
+        // finding the colors on a mapped cube
+        let red = Color::new(1.0, 0.0, 0.0);
+        let yellow = Color::new(1.0, 1.0, 0.0);
+        let brown = Color::new(1.0, 0.5, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let cyan = Color::new(0.0, 1.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let purple = Color::new(1.0, 0.0, 1.0);
+        let white = white();
+        let left = uv_align_check(yellow, cyan, red, blue, brown);
+        let front = uv_align_check(cyan, red, yellow, brown, green);
+        let right = uv_align_check(red, yellow, purple, green, white);
+        let back = uv_align_check(green, purple, cyan, white, blue);
+        let up = uv_align_check(brown, cyan, purple, red, yellow);
+        let down = uv_align_check(purple, brown, green, blue, white);
+        let pattern = cube_map(left, front, right, back, up, down);
+        let scenarios = vec![
+            // left
+            (pt(-1.0, 0.0, 0.0), yellow),
+            (pt(-1.0, 0.9, -0.9), cyan),
+            (pt(-1.0, 0.9, 0.9), red),
+            (pt(-1.0, -0.9, -0.9), blue),
+            (pt(-1.0, -0.9, 0.9), brown),
+            // front
+            (pt(0.0, 0.0, 1.0), cyan),
+            (pt(-0.9, 0.9, 1.0), red),
+            (pt(0.9, 0.9, 1.0), yellow),
+            (pt(-0.9, -0.9, 1.0), brown),
+            (pt(0.9, -0.9, 1.0), green),
+            // right
+            (pt(1.0, 0.0, 0.0), red),
+            (pt(1.0, 0.9, 0.9), yellow),
+            (pt(1.0, 0.9, -0.9), purple),
+            (pt(1.0, -0.9, 0.9), green),
+            (pt(1.0, -0.9, -0.9), white),
+            // back
+            (pt(0.0, 0.0, -1.0), green),
+            (pt(0.9, 0.9, -1.0), purple),
+            (pt(-0.9, 0.9, -1.0), cyan),
+            (pt(0.9, -0.9, -1.0), white),
+            (pt(-0.9, -0.9, -1.0), blue),
+            // up
+            (pt(0.0, 1.0, 0.0), brown),
+            (pt(-0.9, 1.0, -0.9), cyan),
+            (pt(0.9, 1.0, -0.9), purple),
+            (pt(-0.9, 1.0, 0.9), red),
+            (pt(0.9, 1.0, 0.9), yellow),
+            // down
+            (pt(0.0, -1.0, 0.0), purple),
+            (pt(-0.9, -1.0, 0.9), brown),
+            (pt(0.9, -1.0, 0.9), green),
+            (pt(-0.9, -1.0, -0.9), blue),
+            (pt(0.9, -1.0, -0.9), white),
+        ];
+        for (point, color) in scenarios {
+            assert_eq!(pattern.pattern_at(point), color, "{:?}", point);
+        }
+    }
 }
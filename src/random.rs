@@ -0,0 +1,65 @@
+use crate::Tuple;
+
+/// A small pseudo-random number generator (SplitMix64).
+/// It is always seeded explicitly so that renders are reproducible and come
+/// out identical no matter how many threads are used to render them.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    // seed the generator from the bit patterns of a point so that every
+    // point in the scene gets its own repeatable sequence
+    pub fn from_tuple(point: Tuple) -> Rng {
+        let seed = point.x.to_bits()
+            ^ point.y.to_bits().rotate_left(21)
+            ^ point.z.to_bits().rotate_left(42);
+        Rng::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // a number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pt;
+
+    #[test]
+    fn rng_sequence() {
+        // the same seed produces the same sequence
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        // numbers are in the range [0, 1)
+        let mut rng = Rng::from_tuple(pt(1.0, 2.0, 3.0));
+        for _ in 0..1000 {
+            let n = rng.next_f64();
+            assert!((0.0..1.0).contains(&n));
+        }
+
+        // different points produce different sequences
+        let mut a = Rng::from_tuple(pt(1.0, 2.0, 3.0));
+        let mut b = Rng::from_tuple(pt(1.0, 2.0, 3.5));
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+}
@@ -1,14 +1,26 @@
-use crate::{pt, Color, Matrix4x4, Tuple};
+use crate::{Matrix4x4, Tuple};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    // when the ray was cast, from 0.0 (the shutter opening) to 1.0 (the
+    // shutter closing)
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    pub fn at_time(mut self, time: f64) -> Ray {
+        self.time = time;
+        self
     }
 
     pub fn position(&self, t: f64) -> Tuple {
@@ -19,30 +31,7 @@ impl Ray {
         Ray {
             origin: m * self.origin,
             direction: m * self.direction,
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub struct PointLight {
-    pub position: Tuple,
-    pub intensity: Color,
-}
-
-impl PointLight {
-    pub fn new(position: Tuple, intensity: Color) -> PointLight {
-        PointLight {
-            position,
-            intensity,
-        }
-    }
-}
-
-impl Default for PointLight {
-    fn default() -> Self {
-        PointLight {
-            position: pt(0.0, 0.0, 0.0),
-            intensity: Color::new(1.0, 1.0, 1.0),
+            time: self.time,
         }
     }
 }
@@ -60,6 +49,13 @@ mod tests {
         let r = Ray::new(origin, direction);
         assert_eq!(r.origin, origin);
         assert_eq!(r.direction, direction);
+        assert_eq!(r.time, 0.0);
+
+        // a ray cast later while the shutter is open keeps its time when
+        // transformed
+        let r = r.at_time(0.25);
+        assert_eq!(r.time, 0.25);
+        assert_eq!(r.transform(Matrix4x4::scaling(2.0, 2.0, 2.0)).time, 0.25);
     }
 
     #[test]
@@ -87,14 +83,4 @@ mod tests {
         assert_eq!(r2.origin, pt(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, v(0.0, 3.0, 0.0));
     }
-
-    #[test]
-    fn point_light() {
-        // a point light has a position and an intensity
-        let intensity = Color::new(1.0, 1.0, 1.0);
-        let position = pt(0.0, 0.0, 0.0);
-        let light = PointLight::new(position, intensity);
-        assert_eq!(light.position, position);
-        assert_eq!(light.intensity, intensity);
-    }
 }
@@ -1,11 +1,59 @@
+use crate::random::Rng;
 use crate::{
-    lighting, pt, schlick, Canvas, Color, Comps, Intersection, Intersections, Material, Matrix4x4,
-    PointLight, Ray, Shape, Sphere, Tuple,
+    direct_lighting, pt, schlick, white, Canvas, Color, Comps, Group, Intersection, Intersections,
+    Light, Material, Matrix4x4, PointLight, Ray, Shape, Sphere, Tuple, EPSILON,
 };
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The width and height, in pixels, of the tiles handed to each render thread.
+const TILE_SIZE: usize = 16;
+
+/// The most bounces a path can take, even if russian roulette keeps it alive.
+const MAX_PATH_DEPTH: usize = 64;
+
+/// How many bounces a path takes before russian roulette may end it.
+const MIN_PATH_DEPTH: usize = 3;
+
+/// Without path tracing, emissive shapes are sampled at one random point in
+/// each cell of an `EMITTER_STEPS` x `EMITTER_STEPS` grid over their surface.
+const EMITTER_STEPS: usize = 4;
+
+/// How ambient occlusion is estimated. It darkens the ambient light in
+/// creases and corners by casting rays out from the surface and counting how
+/// many of them are blocked nearby.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AmbientOcclusion {
+    // how many rays are cast from every point
+    pub samples: usize,
+    // how close something has to be to block the ambient light
+    pub distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: usize, distance: f64) -> AmbientOcclusion {
+        AmbientOcclusion {
+            samples: samples.max(1),
+            distance,
+        }
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> AmbientOcclusion {
+        AmbientOcclusion::new(16, 1.0)
+    }
+}
 
 pub struct World {
     pub objects: Vec<Box<dyn Shape>>,
-    pub lights: Vec<PointLight>,
+    pub lights: Vec<Light>,
+    // scale the ambient light by how much of it isn't occluded
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    // where to find each emissive shape that is sampled as a light, as the
+    // index into `objects` followed by the index into each nested group
+    emitters: Vec<Vec<usize>>,
 }
 
 impl<'a> World {
@@ -13,6 +61,8 @@ impl<'a> World {
         World {
             objects: Vec::new(),
             lights: Vec::new(),
+            ambient_occlusion: None,
+            emitters: Vec::new(),
         }
     }
 
@@ -26,17 +76,26 @@ impl<'a> World {
     }
 
     pub fn shade_hit(&self, comps: &Comps, remaining: usize) -> Color {
+        let unoccluded = self
+            .ambient_occlusion
+            .map(|settings| self.unoccluded(comps, &settings));
         let colors = self.lights.iter().map(|l| {
             let object = comps.object;
-            let surface = lighting(
-                &object.material(),
-                comps.object,
-                &l,
-                &comps.over_point,
-                &comps.eyev,
-                &comps.normalv,
-                self.is_shadowed(l, comps.over_point),
-            );
+            let intensity = l.intensity_at(comps.over_point, self, comps.time);
+            // the surface's pattern is wherever the shape was at the time
+            let material = object.material();
+            let color = material.color_at(object, comps.over_point, comps.time);
+            let ambient = color * l.intensity() * (material.ambient * unoccluded.unwrap_or(1.0));
+            let surface = ambient
+                + direct_lighting(
+                    material,
+                    color,
+                    l,
+                    &comps.over_point,
+                    &comps.eyev,
+                    &comps.normalv,
+                    intensity,
+                );
             let reflected = self.reflected_color(&comps, remaining);
             let refracted = self.refracted_color(&comps, remaining);
 
@@ -47,7 +106,94 @@ impl<'a> World {
             }
             surface + reflected + refracted
         });
-        colors.sum()
+        let emitted = if self.emitters.is_empty() {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            let mut rng = Rng::from_tuple(comps.over_point);
+            self.emitted_light(comps, EMITTER_STEPS, &mut rng)
+        };
+        colors.sum::<Color>() + comps.object.material().emissive + emitted
+    }
+
+    /// The fraction of the rays cast out from the surface that get further
+    /// than `settings.distance` without hitting anything, from 0.0 (fully
+    /// occluded) to 1.0. The rays are cosine weighted, so those closer to the
+    /// normal count for more.
+    pub fn unoccluded(&self, comps: &Comps, settings: &AmbientOcclusion) -> f64 {
+        // seed from the point so that it is always shaded the same way
+        let mut rng = Rng::from_tuple(comps.over_point);
+        let samples = settings.samples.max(1);
+        let open = (0..samples)
+            .filter(|_| {
+                let direction =
+                    cosine_sample_hemisphere(comps.normalv, rng.next_f64(), rng.next_f64());
+                let end = comps.over_point + direction * settings.distance;
+                !self.is_shadowed(end, comps.over_point, comps.time)
+            })
+            .count();
+        open as f64 / samples as f64
+    }
+
+    /// The emissive shapes that are sampled as lights, as found by the last
+    /// call to `refresh_parents` or `divide`.
+    pub fn emitters(&self) -> impl Iterator<Item = &dyn Shape> + '_ {
+        // skip any emitter the objects have been rearranged out from under
+        self.emitters.iter().filter_map(move |path| {
+            let mut shape: &dyn Shape = &**self.objects.get(path[0])?;
+            for &i in &path[1..] {
+                let group = shape.as_any().downcast_ref::<Group>()?;
+                shape = &**group.children.get(i)?;
+            }
+            Some(shape)
+        })
+    }
+
+    // the light reaching the surface from every emissive shape, estimated
+    // from a random point in each cell of a `steps` x `steps` grid laid over
+    // each of them
+    fn emitted_light(&self, comps: &Comps, steps: usize, rng: &mut Rng) -> Color {
+        let samples = steps * steps;
+        let mut received = Color::new(0.0, 0.0, 0.0);
+        'emitters: for emitter in self.emitters() {
+            let props = emitter.common();
+            let object_to_world = *props.parent_transform() * *props.transform();
+            let determinant = object_to_world.determinant().abs();
+            let mut total = 0.0;
+            for i in 0..samples {
+                let su = ((i % steps) as f64 + rng.next_f64()) / steps as f64;
+                let sv = ((i / steps) as f64 + rng.next_f64()) / steps as f64;
+                let (point, normal, area) = match emitter.sample_surface(su, sv) {
+                    Some(sample) => sample,
+                    None => continue 'emitters,
+                };
+                let point = object_to_world * point;
+                let mut normal = *props.normal_to_world() * normal;
+                normal.w = 0.0;
+                // how much the transforms stretch the surface at the point
+                let area = area * determinant * normal.magnitude();
+
+                let to_light = point - comps.over_point;
+                let distance2 = to_light.dot(&to_light);
+                let lightv = to_light.normalize();
+                let light_dot_normal = lightv.dot(&comps.normalv);
+                if light_dot_normal <= 0.0 {
+                    continue;
+                }
+                // stop just short of the emitter so that it doesn't shadow
+                // the point it is lighting
+                if self.is_shadowed(point - lightv * EPSILON, comps.over_point, comps.time) {
+                    continue;
+                }
+                // both sides of the surface give off light
+                let emitter_dot_light = normal.normalize().dot(&lightv).abs();
+                total += light_dot_normal * emitter_dot_light * area / distance2;
+            }
+            received = received + emitter.material().emissive * (total / samples as f64);
+        }
+        let material = comps.object.material();
+        material.color_at(comps.object, comps.over_point, comps.time)
+            * received
+            * (material.diffuse / PI)
     }
 
     pub fn color_at(&self, ray: &Ray, remaining: usize) -> Color {
@@ -62,12 +208,18 @@ impl<'a> World {
         Color::new(0.0, 0.0, 0.0)
     }
 
-    pub fn is_shadowed(&self, light: &PointLight, point: Tuple) -> bool {
-        let v = light.position - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
+    /// Whether anything lies between `point` and the light at `time`. A
+    /// vector for `light_position` is a direction towards a light infinitely
+    /// far away.
+    pub fn is_shadowed(&self, light_position: Tuple, point: Tuple, time: f64) -> bool {
+        let (distance, direction) = if light_position.is_vector() {
+            (f64::INFINITY, light_position.normalize())
+        } else {
+            let v = light_position - point;
+            (v.magnitude(), v.normalize())
+        };
 
-        let r = Ray::new(point, direction);
+        let r = Ray::new(point, direction).at_time(time);
         let intersections = self.intersect(&r);
 
         let intersections: Vec<Intersection> = intersections
@@ -90,7 +242,7 @@ impl<'a> World {
         if comps.object.material().reflective == 0.0 {
             return Color::new(0.0, 0.0, 0.0);
         }
-        let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
+        let reflect_ray = Ray::new(comps.over_point, comps.reflectv).at_time(comps.time);
         let color = self.color_at(&reflect_ray, remaining - 1);
         color * comps.object.material().reflective
     }
@@ -102,37 +254,141 @@ impl<'a> World {
         if comps.object.material().transparency == 0.0 {
             return Color::new(0.0, 0.0, 0.0);
         }
-        // find the ratio of the first index of refraction to the second
-        // inverted from the definition of Snell's Law
-        let n_ratio = comps.n1 / comps.n2;
-
-        // cos(theta_i) is the same as the dot product of the two vectors
-        let cos_i = comps.eyev.dot(&comps.normalv);
-
-        // find sin(theta_t) ^ 2 via trigonometric identity
-        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
-        if sin2_t > 1.0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-
-        // cos(theta_t) via trigonometric identity
-        let cos_t = (1.0 - sin2_t).sqrt();
-
-        // compute the direction of the refracted ray
-        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        let direction = match refraction_direction(comps) {
+            Some(direction) => direction,
+            None => return Color::new(0.0, 0.0, 0.0),
+        };
 
         // create the refracted ray
-        let refract_ray = Ray::new(comps.under_point, direction);
+        let refract_ray = Ray::new(comps.under_point, direction).at_time(comps.time);
 
         // find the color of the refracted ray, making sure to multiply
         // by the transparency value to account for any opacity
         self.color_at(&refract_ray, remaining - 1) * comps.object.material().transparency
     }
 
+    /// The color seen along `ray` by following a single random path through
+    /// the scene. Every surface the path meets adds the light it receives
+    /// directly from `lights`, and then the path continues in one direction
+    /// picked at random from how the material scatters light. Averaging many
+    /// paths gives indirect light, such as color bleeding between diffuse
+    /// surfaces, in place of the constant ambient term.
+    pub(crate) fn trace_path(&self, ray: &Ray, rng: &mut Rng) -> Color {
+        let mut radiance = Color::new(0.0, 0.0, 0.0);
+        // how much of the light arriving at the current surface makes it
+        // back to the eye
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // emissive shapes that are sampled as lights have already added
+        // their light at the last surface, unless it was a mirror or glass
+        let mut specular_bounce = true;
+
+        for depth in 0..MAX_PATH_DEPTH {
+            let intersections = self.intersect(&ray);
+            let hit = match intersections.hit() {
+                Some(hit) => hit,
+                None => break,
+            };
+            let comps = hit.prepare_computations(&ray, &intersections);
+            let material = comps.object.material();
+            if specular_bounce || !is_emitter(comps.object) {
+                radiance = radiance + throughput * material.emissive;
+            }
+
+            let color = material.color_at(comps.object, comps.over_point, comps.time);
+            for light in &self.lights {
+                let direct = direct_lighting(
+                    material,
+                    color,
+                    light,
+                    &comps.over_point,
+                    &comps.eyev,
+                    &comps.normalv,
+                    light.intensity_at(comps.over_point, self, comps.time),
+                );
+                radiance = radiance + throughput * direct;
+            }
+            if !self.emitters.is_empty() {
+                radiance = radiance + throughput * self.emitted_light(&comps, 1, rng);
+            }
+
+            // weigh each way of scattering the same way `shade_hit` does
+            let albedo = color * material.diffuse;
+            let (reflective, transparency) =
+                if material.reflective > 0.0 && material.transparency > 0.0 {
+                    let reflectance = schlick(&comps);
+                    (reflectance, 1.0 - reflectance)
+                } else {
+                    (material.reflective, material.transparency)
+                };
+            let diffuse = max_component(albedo);
+            let total = diffuse + reflective + transparency;
+            if total <= 0.0 {
+                break;
+            }
+
+            // pick one of them in proportion to its weight, and divide by the
+            // chance of having picked it to keep the estimate unbiased
+            let choice = rng.next_f64() * total;
+            let (direction, origin, weight) = if choice < diffuse {
+                let direction =
+                    cosine_sample_hemisphere(comps.normalv, rng.next_f64(), rng.next_f64());
+                specular_bounce = false;
+                (direction, comps.over_point, albedo * (total / diffuse))
+            } else if choice < diffuse + reflective {
+                specular_bounce = true;
+                (comps.reflectv, comps.over_point, white() * total)
+            } else {
+                specular_bounce = true;
+                match refraction_direction(&comps) {
+                    Some(direction) => (direction, comps.under_point, white() * total),
+                    None => break,
+                }
+            };
+            throughput = throughput * weight;
+
+            // randomly end paths that carry little light, boosting the ones
+            // that survive to make up for the ones that don't
+            if depth >= MIN_PATH_DEPTH {
+                let survival = max_component(throughput).min(1.0);
+                if rng.next_f64() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+
+            ray = Ray::new(origin, direction).at_time(comps.time);
+        }
+        radiance
+    }
+
+    /// Updates the transforms and materials that shapes take from their
+    /// groups, and finds the emissive shapes to sample as lights.
     pub fn refresh_parents(&mut self) {
         for object in self.objects.iter_mut() {
             object.refresh_parents();
         }
+        self.refresh_emitters();
+    }
+
+    fn refresh_emitters(&mut self) {
+        self.emitters.clear();
+        for (i, object) in self.objects.iter().enumerate() {
+            find_emitters(&**object, &mut vec![i], &mut self.emitters);
+        }
+    }
+
+    /// Builds a bounding volume hierarchy over the world's objects, and over
+    /// the children of every group, so that rays can skip whole branches.
+    pub fn divide(&mut self, threshold: usize) {
+        let mut root = Group::new();
+        for object in self.objects.drain(..) {
+            root.add_child(object);
+        }
+        root.divide(threshold);
+        self.objects = root.children;
+        // the emitters have moved into the new groups
+        self.refresh_emitters();
     }
 }
 
@@ -149,11 +405,78 @@ impl Default for World {
         let s2 = Sphere::new().transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
         World {
             objects: vec![s1.into(), s2.into()],
-            lights: vec![light],
+            lights: vec![light.into()],
+            ambient_occlusion: None,
+            emitters: Vec::new(),
+        }
+    }
+}
+
+// whether the shape gives off light and can be sampled as a light source
+fn is_emitter(shape: &dyn Shape) -> bool {
+    shape.material().emissive != Color::new(0.0, 0.0, 0.0)
+        && shape.sample_surface(0.5, 0.5).is_some()
+}
+
+fn find_emitters(shape: &dyn Shape, path: &mut Vec<usize>, emitters: &mut Vec<Vec<usize>>) {
+    if let Some(group) = shape.as_any().downcast_ref::<Group>() {
+        for (i, child) in group.children.iter().enumerate() {
+            path.push(i);
+            find_emitters(&**child, path, emitters);
+            path.pop();
         }
+    } else if is_emitter(shape) {
+        emitters.push(path.clone());
     }
 }
 
+// the direction of the ray refracted through the surface, or None when all
+// of the light is reflected
+fn refraction_direction(comps: &Comps) -> Option<Tuple> {
+    // find the ratio of the first index of refraction to the second
+    // inverted from the definition of Snell's Law
+    let n_ratio = comps.n1 / comps.n2;
+
+    // cos(theta_i) is the same as the dot product of the two vectors
+    let cos_i = comps.eyev.dot(&comps.normalv);
+
+    // find sin(theta_t) ^ 2 via trigonometric identity
+    let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+
+    // cos(theta_t) via trigonometric identity
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    // compute the direction of the refracted ray
+    Some(comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio)
+}
+
+// a random direction on the hemisphere around `normal`, more likely to be
+// close to the normal in proportion to the cosine of the angle between them
+fn cosine_sample_hemisphere(normal: Tuple, u: f64, v: f64) -> Tuple {
+    // any two vectors perpendicular to the normal and to each other
+    let other = if normal.x.abs() > 0.9 {
+        Tuple::vector(0.0, 1.0, 0.0)
+    } else {
+        Tuple::vector(1.0, 0.0, 0.0)
+    };
+    let tangent = (other * normal).normalize();
+    let bitangent = normal * tangent;
+
+    // sample a disk and project it up onto the hemisphere
+    let radius = u.sqrt();
+    let theta = 2.0 * PI * v;
+    let (x, y) = (radius * theta.cos(), radius * theta.sin());
+    let z = (1.0 - u).max(0.0).sqrt();
+    (tangent * x + bitangent * y + normal * z).normalize()
+}
+
+fn max_component(color: Color) -> f64 {
+    color.red.max(color.green).max(color.blue)
+}
+
 pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4x4 {
     let forward = (to - from).normalize();
     let upn = up.normalize();
@@ -168,11 +491,49 @@ pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4x4 {
     orientation * Matrix4x4::translation(-from.x, -from.y, -from.z)
 }
 
+/// How many rays are cast through each pixel, and where.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Sampling {
+    // a single ray through the center of the pixel
+    Center,
+    // an n x n grid of evenly spaced rays
+    Grid(usize),
+    // an n x n grid with each ray placed randomly within its cell
+    Jittered(usize),
+    // sample the corners of the pixel and keep subdividing, up to `depth`
+    // times, while they differ by more than `threshold`
+    Adaptive { threshold: f64, depth: usize },
+}
+
+/// How the color seen along each ray is worked out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Integrator {
+    // direct light from every light plus a constant ambient term, following
+    // only mirror reflections and refractions
+    Whitted,
+    // average this many randomly traced paths through the scene for every
+    // point on the canvas, picking up indirect light along the way
+    PathTraced(usize),
+}
+
 pub struct Camera {
     pub hsize: usize,
     pub vsize: usize,
     pub field_of_view: f64,
-    pub transform: Matrix4x4,
+    pub sampling: Sampling,
+    pub integrator: Integrator,
+    // the diameter of the lens; zero makes a pinhole camera with
+    // everything in focus
+    pub aperture: f64,
+    // the distance from the camera to the plane that is in perfect focus
+    pub focal_distance: f64,
+    // how many points on the lens are sampled for every point on the canvas
+    pub lens_samples: usize,
+    // how many moments between the shutter opening and closing are sampled
+    // for every point on the canvas; more than one gives motion blur
+    pub time_samples: usize,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
     pixel_size: f64,
     half_width: f64,
     half_height: f64,
@@ -201,23 +562,70 @@ impl Camera {
             hsize: hsize as usize,
             vsize: vsize as usize,
             field_of_view,
+            sampling: Sampling::Center,
+            integrator: Integrator::Whitted,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            lens_samples: 16,
+            time_samples: 1,
             transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
             half_width,
             half_height,
             pixel_size,
         }
     }
 
-    pub fn transform(&mut self, transform: Matrix4x4) {
+    pub fn transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4x4) {
         self.transform = transform;
+        self.inverse = transform.inverse().unwrap();
+    }
+
+    pub fn sampling(mut self, sampling: Sampling) -> Camera {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn integrator(mut self, integrator: Integrator) -> Camera {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn aperture(mut self, aperture: f64) -> Camera {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn focal_distance(mut self, focal_distance: f64) -> Camera {
+        self.focal_distance = focal_distance;
+        self
+    }
+
+    pub fn lens_samples(mut self, lens_samples: usize) -> Camera {
+        self.lens_samples = lens_samples.max(1);
+        self
+    }
+
+    pub fn time_samples(mut self, time_samples: usize) -> Camera {
+        self.time_samples = time_samples.max(1);
+        self
     }
 
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let px = px as f64;
-        let py = py as f64;
-        // the offset from the edge of the canvas to the pixel's center
-        let xoffset = (px + 0.5) * self.pixel_size;
-        let yoffset = (py + 0.5) * self.pixel_size;
+        // shoot the ray through the pixel's center
+        self.ray_for_point(px as f64 + 0.5, py as f64 + 0.5)
+    }
+
+    /// The ray through a point on the canvas, measured in pixels from the
+    /// top left corner of the canvas.
+    pub fn ray_for_point(&self, x: f64, y: f64) -> Ray {
+        // the offset from the edge of the canvas to the point
+        let xoffset = x * self.pixel_size;
+        let yoffset = y * self.pixel_size;
 
         // the untransformed coordinates of the pixel in world space
         // remember that the camera looks toward -z, so +x is to the *left*
@@ -226,32 +634,239 @@ impl Camera {
 
         // using the camera matrix, transform the canvas point and the origin
         // and then compute the ray's direction vector
-        let pixel = self.transform.inverse().unwrap() * pt(world_x, world_y, -1.0);
-        let origin = self.transform.inverse().unwrap() * pt(0.0, 0.0, 0.0);
+        let pixel = self.inverse * pt(world_x, world_y, -1.0);
+        let origin = self.inverse * pt(0.0, 0.0, 0.0);
         let direction = (pixel - origin).normalize();
 
         Ray::new(origin, direction)
     }
 
+    /// The ray from a point on the lens through a point on the canvas. `u`
+    /// and `v` pick the point on the lens and range from 0.0 to 1.0. The ray
+    /// is aimed at where the pinhole ray meets the focal plane, so only
+    /// objects at the focal distance are sharp.
+    pub fn ray_through_lens(&self, x: f64, y: f64, u: f64, v: f64) -> Ray {
+        if self.aperture == 0.0 {
+            return self.ray_for_point(x, y);
+        }
+        let world_x = self.half_width - x * self.pixel_size;
+        let world_y = self.half_height - y * self.pixel_size;
+
+        // the canvas is one unit away, so scaling the canvas point by the
+        // focal distance moves it onto the focal plane
+        let distance = self.focal_distance;
+        let focus = self.inverse * pt(world_x * distance, world_y * distance, -distance);
+
+        // uniformly sample the disk of the lens
+        let radius = self.aperture / 2.0 * u.sqrt();
+        let theta = 2.0 * PI * v;
+        let origin = self.inverse * pt(radius * theta.cos(), radius * theta.sin(), 0.0);
+        let direction = (focus - origin).normalize();
+
+        Ray::new(origin, direction)
+    }
+
+    fn color_at_point(&self, world: &World, x: f64, y: f64) -> Color {
+        let lens_samples = if self.aperture == 0.0 {
+            1
+        } else {
+            self.lens_samples
+        };
+        let mut samples = lens_samples.max(self.time_samples);
+        if let Integrator::PathTraced(paths) = self.integrator {
+            samples = samples.max(paths);
+        } else if samples == 1 {
+            let ray = self.ray_for_point(x, y);
+            return world.color_at(&ray, 4);
+        }
+        // seed from the canvas point so every render of it is the same
+        let mut rng = Rng::from_tuple(pt(x, y, 0.0));
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        for i in 0..samples {
+            let (u, v) = (rng.next_f64(), rng.next_f64());
+            // spread the samples evenly over the time the shutter is open
+            let time = if self.time_samples > 1 {
+                (i as f64 + rng.next_f64()) / samples as f64
+            } else {
+                0.0
+            };
+            let ray = self.ray_through_lens(x, y, u, v).at_time(time);
+            color = color
+                + match self.integrator {
+                    Integrator::Whitted => world.color_at(&ray, 4),
+                    Integrator::PathTraced(_) => world.trace_path(&ray, &mut rng),
+                };
+        }
+        color / samples as f64
+    }
+
+    fn render_pixel(&self, world: &World, px: usize, py: usize) -> Color {
+        let (x, y) = (px as f64, py as f64);
+        match self.sampling {
+            Sampling::Center => self.color_at_point(world, x + 0.5, y + 0.5),
+            Sampling::Grid(n) | Sampling::Jittered(n) => {
+                let n = n.max(1);
+                let jitter = matches!(self.sampling, Sampling::Jittered(_));
+                // seed from the pixel so every render of it is the same
+                let mut rng = Rng::new((py * self.hsize + px) as u64);
+                let mut color = Color::new(0.0, 0.0, 0.0);
+                for j in 0..n {
+                    for i in 0..n {
+                        let (ju, jv) = if jitter {
+                            (rng.next_f64(), rng.next_f64())
+                        } else {
+                            (0.5, 0.5)
+                        };
+                        let sx = x + (i as f64 + ju) / n as f64;
+                        let sy = y + (j as f64 + jv) / n as f64;
+                        color = color + self.color_at_point(world, sx, sy);
+                    }
+                }
+                color / (n * n) as f64
+            }
+            Sampling::Adaptive { threshold, depth } => {
+                self.adaptive_sample(world, x, y, 1.0, threshold, depth)
+            }
+        }
+    }
+
+    // average the corners of a square of the canvas, splitting it into four
+    // smaller squares when the corners don't agree
+    fn adaptive_sample(
+        &self,
+        world: &World,
+        x: f64,
+        y: f64,
+        size: f64,
+        threshold: f64,
+        depth: usize,
+    ) -> Color {
+        let corners = [
+            self.color_at_point(world, x, y),
+            self.color_at_point(world, x + size, y),
+            self.color_at_point(world, x, y + size),
+            self.color_at_point(world, x + size, y + size),
+        ];
+        let average = corners.iter().copied().sum::<Color>() / 4.0;
+        let differs = corners.iter().any(|c| {
+            (c.red - average.red).abs() > threshold
+                || (c.green - average.green).abs() > threshold
+                || (c.blue - average.blue).abs() > threshold
+        });
+        if depth == 0 || !differs {
+            return average;
+        }
+        let half = size / 2.0;
+        let colors = [
+            self.adaptive_sample(world, x, y, half, threshold, depth - 1),
+            self.adaptive_sample(world, x + half, y, half, threshold, depth - 1),
+            self.adaptive_sample(world, x, y + half, half, threshold, depth - 1),
+            self.adaptive_sample(world, x + half, y + half, half, threshold, depth - 1),
+        ];
+        colors.iter().copied().sum::<Color>() / 4.0
+    }
+
     pub fn render(&self, world: &mut World) -> Canvas {
         world.refresh_parents();
         let mut image = Canvas::new(self.hsize, self.vsize);
 
         for y in 0..self.vsize {
             for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(&ray, 4);
+                let color = self.render_pixel(world, x, y);
                 image.write_pixel(x, y, color);
             }
         }
         image
     }
+
+    /// Renders how much ambient light reaches the point seen through the
+    /// center of each pixel, from black where it is fully occluded to white
+    /// where nothing is in the way, for compositing with other renders. Uses
+    /// the world's ambient occlusion settings, or the defaults if it has none.
+    pub fn render_ambient_occlusion(&self, world: &mut World) -> Canvas {
+        world.refresh_parents();
+        let settings = world.ambient_occlusion.unwrap_or_default();
+        let mut image = Canvas::new(self.hsize, self.vsize);
+
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let ray = self.ray_for_pixel(x, y);
+                let intersections = world.intersect(&ray);
+                let unoccluded = match intersections.hit() {
+                    Some(hit) => {
+                        let comps = hit.prepare_computations(&ray, &intersections);
+                        world.unoccluded(&comps, &settings)
+                    }
+                    None => 1.0,
+                };
+                image.write_pixel(x, y, Color::new(unoccluded, unoccluded, unoccluded));
+            }
+        }
+        image
+    }
+
+    /// Renders the image in square tiles of `TILE_SIZE` pixels spread across
+    /// `threads` worker threads. Each pixel is computed exactly as `render`
+    /// computes it, so the resulting canvas is identical.
+    pub fn render_parallel(&self, world: &mut World, threads: usize) -> Canvas {
+        world.refresh_parents();
+        let world: &World = world;
+
+        // the top left corner of every tile, handed out to workers in order
+        let mut tiles = Vec::new();
+        for y in (0..self.vsize).step_by(TILE_SIZE) {
+            for x in (0..self.hsize).step_by(TILE_SIZE) {
+                tiles.push((x, y));
+            }
+        }
+        let next_tile = AtomicUsize::new(0);
+
+        let rendered: Vec<(usize, usize, Vec<Color>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut rendered = Vec::new();
+                        loop {
+                            let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                            if tile >= tiles.len() {
+                                break;
+                            }
+                            let (x0, y0) = tiles[tile];
+                            let x1 = (x0 + TILE_SIZE).min(self.hsize);
+                            let y1 = (y0 + TILE_SIZE).min(self.vsize);
+                            let mut colors = Vec::with_capacity((x1 - x0) * (y1 - y0));
+                            for y in y0..y1 {
+                                for x in x0..x1 {
+                                    colors.push(self.render_pixel(world, x, y));
+                                }
+                            }
+                            rendered.push((x0, y0, colors));
+                        }
+                        rendered
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("render thread panicked"))
+                .collect()
+        });
+
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for (x0, y0, colors) in rendered {
+            let width = (x0 + TILE_SIZE).min(self.hsize) - x0;
+            for (i, color) in colors.into_iter().enumerate() {
+                image.write_pixel(x0 + i % width, y0 + i / width, color);
+            }
+        }
+        image
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{black, test_pattern, v, Plane, Sphere};
+    use crate::{black, test_pattern, v, white, Cube, Plane, Sphere};
     use std::f64::consts::PI;
 
     fn twosqrttwo() -> f64 {
@@ -278,7 +893,7 @@ mod tests {
         let s1 = Sphere::new().material(material);
         let s2 = Sphere::new().transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
         let w = World::default();
-        assert_eq!(w.lights[0], light);
+        assert_eq!(w.lights[0], light.into());
         assert_eq!(w.objects.contains(&s1.into()), true);
         assert_eq!(w.objects.contains(&s2.into()), true);
     }
@@ -308,10 +923,7 @@ mod tests {
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
 
         // shading an intersection from the inside
-        w.lights = vec![PointLight::new(
-            pt(0.0, 0.25, 0.0),
-            Color::new(1.0, 1.0, 1.0),
-        )];
+        w.lights = vec![PointLight::new(pt(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)).into()];
         let r = Ray::new(pt(0.0, 0.0, 0.0), v(0.0, 0.0, 1.0));
         let shape = &w.objects[1];
         let i = Intersection::new(0.5, &**shape);
@@ -348,15 +960,13 @@ mod tests {
 
         // color_at() with mutually reflective surfaces
         let mut w = World::empty();
-        w.lights.push(PointLight::new(
-            pt(0.0, 0.0, 0.0),
-            Color::new(1.0, 1.0, 1.0),
-        ));
+        w.lights
+            .push(PointLight::new(pt(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
         let mut material = Material::new();
         material.reflective = 1.0;
         let lower = Plane::new()
             .transform(Matrix4x4::translation(0.0, -1.0, 0.0))
-            .material(material);
+            .material(material.clone());
         let upper = Plane::new()
             .transform(Matrix4x4::translation(0.0, 1.0, 0.0))
             .material(material);
@@ -365,6 +975,38 @@ mod tests {
         w.color_at(&r, 1);
     }
 
+    #[test]
+    fn world_divide() {
+        // dividing a world keeps every intersection
+        let mut w = World::default();
+        for x in 0..4 {
+            let t = Matrix4x4::translation(x as f64 * 3.0 - 6.0, 0.0, 2.0);
+            w.objects.push(Sphere::new().transform(t).into());
+        }
+        w.objects.push(
+            Plane::new()
+                .transform(Matrix4x4::translation(0.0, -1.0, 0.0))
+                .into(),
+        );
+        let rays = [
+            Ray::new(pt(0.0, 0.0, -5.0), v(0.0, 0.0, 1.0)),
+            Ray::new(pt(-6.0, 0.0, -5.0), v(0.0, 0.0, 1.0)),
+            Ray::new(pt(0.0, 5.0, -5.0), v(0.0, -1.0, 1.0).normalize()),
+        ];
+        let before: Vec<Vec<f64>> = rays
+            .iter()
+            .map(|r| w.intersect(r).iter().map(|i| i.t).collect())
+            .collect();
+        w.divide(2);
+        w.refresh_parents();
+        assert!(w.objects.len() < 7);
+        let after: Vec<Vec<f64>> = rays
+            .iter()
+            .map(|r| w.intersect(r).iter().map(|i| i.t).collect())
+            .collect();
+        assert_eq!(before, after);
+    }
+
     #[test]
     fn view_transform_() {
         // the transformation matrix for the default orientation
@@ -420,7 +1062,7 @@ mod tests {
         assert_eq!(c.hsize, hsize);
         assert_eq!(c.vsize, vsize);
         assert_eq!(c.field_of_view, PI / 2.0);
-        assert_eq!(c.transform, Matrix4x4::identity());
+        assert_eq!(c.transform(), &Matrix4x4::identity());
 
         // the pixel size for a horizontal canvas
         let c = Camera::new(200, 125, PI / 2.0);
@@ -445,7 +1087,7 @@ mod tests {
         assert_eq!(r.direction, v(0.66519, 0.33259, -0.66851));
 
         // constructing a ray when the camera is transformed
-        c.transform = Matrix4x4::rotation_y(PI / 4.0) * Matrix4x4::translation(0.0, -2.0, 5.0);
+        c.set_transform(Matrix4x4::rotation_y(PI / 4.0) * Matrix4x4::translation(0.0, -2.0, 5.0));
         let r = c.ray_for_pixel(100, 50);
         assert_eq!(r.origin, pt(0.0, 2.0, -5.0));
         assert_eq!(
@@ -462,39 +1104,457 @@ mod tests {
         let from = pt(0.0, 0.0, -5.0);
         let to = pt(0.0, 0.0, 0.0);
         let up = v(0.0, 1.0, 0.0);
-        c.transform = view_transform(from, to, up);
+        c.set_transform(view_transform(from, to, up));
         let image = c.render(&mut w);
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
 
+    #[test]
+    fn camera_sampling() {
+        // a ray through a point on the canvas
+        let c = Camera::new(201, 101, PI / 2.0);
+        assert_eq!(c.sampling, Sampling::Center);
+        assert_eq!(c.ray_for_point(100.5, 50.5), c.ray_for_pixel(100, 50));
+        assert_eq!(c.ray_for_point(0.5, 0.5), c.ray_for_pixel(0, 0));
+
+        // a 1x1 grid is the same as sampling the center of each pixel
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            pt(0.0, 0.0, -5.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        let expected = c.render(&mut w);
+        let c = c.sampling(Sampling::Grid(1));
+        let image = c.render(&mut w);
+        for y in 0..c.vsize {
+            for x in 0..c.hsize {
+                assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+            }
+        }
+
+        // a single pixel split down the middle by the edge of a white cube
+        let mut w = World::empty();
+        w.lights
+            .push(PointLight::new(pt(0.0, 0.0, -10.0), white()).into());
+        let material = Material::new().ambient(1.0).diffuse(0.0).specular(0.0);
+        w.objects.push(
+            Cube::new()
+                .transform(Matrix4x4::translation(1.0, 0.0, 0.0))
+                .material(material)
+                .into(),
+        );
+        let mut c = Camera::new(1, 1, 0.4);
+        c.set_transform(view_transform(
+            pt(0.0, 0.0, -5.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        let gray = Color::new(0.5, 0.5, 0.5);
+
+        // supersampling on a grid blends the colors on either side of an edge
+        let c = c.sampling(Sampling::Grid(2));
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), gray);
+        let c = c.sampling(Sampling::Grid(4));
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), gray);
+
+        // jittered samples also blend the edge and are repeatable
+        let c = c.sampling(Sampling::Jittered(4));
+        let color = c.render(&mut w).pixel_at(0, 0);
+        assert!(color.red > 0.2 && color.red < 0.8);
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), color);
+        assert_eq!(c.render_parallel(&mut w, 2).pixel_at(0, 0), color);
+
+        // adaptive sampling averages the corners of the pixel
+        let c = c.sampling(Sampling::Adaptive {
+            threshold: 0.1,
+            depth: 0,
+        });
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), gray);
+
+        // adaptive sampling doesn't subdivide when the corners agree
+        let c = c.sampling(Sampling::Adaptive {
+            threshold: 1.0,
+            depth: 4,
+        });
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), gray);
+    }
+
+    #[test]
+    fn camera_depth_of_field() {
+        // a pinhole camera ignores the point on the lens
+        let c = Camera::new(201, 101, PI / 2.0);
+        assert_eq!(c.aperture, 0.0);
+        assert_eq!(
+            c.ray_through_lens(100.5, 50.5, 0.7, 0.3),
+            c.ray_for_point(100.5, 50.5)
+        );
+
+        // rays through every point on the lens meet at the focal plane
+        let c = Camera::new(201, 101, PI / 2.0)
+            .aperture(1.0)
+            .focal_distance(5.0);
+        let r = c.ray_through_lens(100.5, 50.5, 1.0, 0.0);
+        assert_eq!(r.origin, pt(0.5, 0.0, 0.0));
+        assert_eq!(r.position(25.25_f64.sqrt()), pt(0.0, 0.0, -5.0));
+        let r = c.ray_through_lens(100.5, 50.5, 0.25, 0.25);
+        assert_eq!(r.origin, pt(0.0, 0.25, 0.0));
+        assert_eq!(r.position(25.0625_f64.sqrt()), pt(0.0, 0.0, -5.0));
+
+        // blurring is repeatable and the same when rendered in parallel
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0)
+            .aperture(0.5)
+            .focal_distance(3.0)
+            .lens_samples(4);
+        c.set_transform(view_transform(
+            pt(0.0, 0.0, -5.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        let image = c.render(&mut w);
+        assert_eq!(c.render(&mut w), image);
+        assert_eq!(c.render_parallel(&mut w, 3), image);
+    }
+
+    #[test]
+    fn camera_motion_blur() {
+        // a sphere moving across a pixel during the exposure blurs it
+        let mut w = World::empty();
+        w.lights
+            .push(PointLight::new(pt(0.0, 0.0, -10.0), white()).into());
+        let material = Material::new().ambient(1.0).diffuse(0.0).specular(0.0);
+        w.objects.push(
+            Sphere::new()
+                .transform(
+                    Matrix4x4::translation(-1.0, 0.0, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+                )
+                .transform_end(
+                    Matrix4x4::translation(1.0, 0.0, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+                )
+                .material(material)
+                .into(),
+        );
+        let mut c = Camera::new(1, 1, 0.1);
+        c.set_transform(view_transform(
+            pt(0.0, 0.0, -5.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        // without motion blur only the start of the exposure is seen
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), black());
+
+        let c = c.time_samples(16);
+        let color = c.render(&mut w).pixel_at(0, 0);
+        assert!(color.red > 0.2 && color.red < 0.8, "{:?}", color);
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), color);
+        assert_eq!(c.render_parallel(&mut w, 2).pixel_at(0, 0), color);
+    }
+
+    #[test]
+    fn camera_path_tracing() {
+        // a floor lit from a light between it and a ceiling
+        let mut w = World::empty();
+        w.lights
+            .push(PointLight::new(pt(0.0, 1.0, 0.0), white()).into());
+        let material = Material::new().ambient(0.0).specular(0.0);
+        w.objects
+            .push(Plane::new().material(material.clone()).into());
+        w.objects.push(
+            Plane::new()
+                .transform(Matrix4x4::translation(0.0, 2.0, 0.0))
+                .material(material)
+                .into(),
+        );
+        let mut c = Camera::new(1, 1, 0.1);
+        c.set_transform(view_transform(
+            pt(0.0, 1.5, -3.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        assert_eq!(c.integrator, Integrator::Whitted);
+        let direct = c.render(&mut w).pixel_at(0, 0);
+
+        // light bouncing off the ceiling brightens the floor
+        let c = c.integrator(Integrator::PathTraced(64));
+        let color = c.render(&mut w).pixel_at(0, 0);
+        assert!(color.red > direct.red, "{:?} {:?}", color, direct);
+        assert_eq!(c.render(&mut w).pixel_at(0, 0), color);
+        assert_eq!(c.render_parallel(&mut w, 2).pixel_at(0, 0), color);
+
+        // the ambient term is not used
+        let mut w = World::empty();
+        w.objects
+            .push(Sphere::new().material(Material::new().ambient(1.0)).into());
+        let r = Ray::new(pt(0.0, 0.0, -5.0), v(0.0, 0.0, 1.0));
+        let mut rng = Rng::new(0);
+        assert_eq!(w.trace_path(&r, &mut rng), black());
+    }
+
+    #[test]
+    fn world_emissive_shapes() {
+        // an emissive surface gives off its own light even without lights
+        let mut w = World::empty();
+        let glow = Material::new().emissive(Color::new(1.0, 0.5, 0.0));
+        w.objects.push(
+            Sphere::new()
+                .transform(
+                    Matrix4x4::translation(0.0, 2.0, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+                )
+                .material(glow)
+                .into(),
+        );
+        w.objects.push(
+            Plane::new()
+                .material(Material::new().emissive(white()))
+                .into(),
+        );
+        let mut g = Group::new();
+        g.add_child(Cube::new().shape());
+        w.objects.push(g.shape());
+        w.refresh_parents();
+        let r = Ray::new(pt(0.0, 2.0, -5.0), v(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 4), Color::new(1.0, 0.5, 0.0));
+
+        // only shapes that can be sampled are used as lights, including the
+        // ones inside groups
+        let emitters: Vec<&dyn Shape> = w.emitters().collect();
+        assert_eq!(emitters.len(), 1);
+        assert_eq!(emitters[0], &*w.objects[0]);
+        let glowing_cube = Cube::new().material(Material::new().emissive(white()));
+        w.objects[2]
+            .as_any_mut()
+            .downcast_mut::<Group>()
+            .unwrap()
+            .add_child(glowing_cube.shape());
+        w.refresh_parents();
+        assert_eq!(w.emitters().count(), 2);
+
+        // dividing the world into groups keeps track of where they went
+        for i in 0..4 {
+            let x = i as f64 * 3.0;
+            w.objects.push(
+                Sphere::new()
+                    .transform(Matrix4x4::translation(x, 0.0, 0.0))
+                    .into(),
+            );
+        }
+        w.refresh_parents();
+        w.divide(1);
+        let emissive: Vec<Color> = w.emitters().map(|e| e.material().emissive).collect();
+        assert_eq!(emissive.len(), 2);
+        assert!(emissive.contains(&Color::new(1.0, 0.5, 0.0)));
+        assert!(emissive.contains(&white()));
+
+        // the light of a sphere reaching the point right underneath it
+        let mut w = World::empty();
+        w.objects.push(Plane::new().into());
+        w.objects.push(
+            Sphere::new()
+                .transform(
+                    Matrix4x4::translation(0.0, 2.0, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+                )
+                .material(Material::new().emissive(white()))
+                .into(),
+        );
+        w.refresh_parents();
+        let r = Ray::new(pt(0.0, 1.0, -1.0), v(0.0, -1.0, 1.0).normalize());
+        let xs = w.intersect(&r);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        let mut rng = Rng::new(0);
+        let color = w.emitted_light(&comps, 100, &mut rng);
+        // the sphere covers a cone of half angle asin(r / d)
+        let expected = 0.9 * (0.5 / 2.0_f64).powi(2);
+        assert!(
+            (color.red - expected).abs() < expected * 0.05,
+            "{:?}",
+            color
+        );
+        assert!(w.color_at(&r, 4).red > 0.0);
+
+        // blocking the sphere casts a shadow
+        w.objects.push(
+            Cube::new()
+                .transform(
+                    Matrix4x4::translation(0.0, 1.0, 0.0) * Matrix4x4::scaling(1.0, 0.1, 1.0),
+                )
+                .into(),
+        );
+        w.refresh_parents();
+        let xs = w.intersect(&r);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        assert_eq!(w.emitted_light(&comps, 10, &mut rng), black());
+    }
+
+    #[test]
+    fn path_tracing_emissive_shapes() {
+        // a glowing sphere lights the floor with no other lights
+        let mut w = World::empty();
+        w.objects.push(Plane::new().into());
+        w.objects.push(
+            Sphere::new()
+                .transform(Matrix4x4::translation(0.0, 2.0, 0.0))
+                .material(Material::new().emissive(white()))
+                .into(),
+        );
+        w.refresh_parents();
+        let mut rng = Rng::new(0);
+        let r = Ray::new(pt(0.0, 1.0, -1.0), v(0.0, -1.0, 1.0).normalize());
+        let color: Color = (0..16).map(|_| w.trace_path(&r, &mut rng)).sum();
+        assert!(color.red > 0.0);
+
+        // the sphere itself is seen in its own color
+        let r = Ray::new(pt(0.0, 2.0, -5.0), v(0.0, 0.0, 1.0));
+        assert!(w.trace_path(&r, &mut Rng::new(0)).red >= 1.0);
+    }
+
+    #[test]
+    fn world_ambient_occlusion() {
+        // a floor next to a wall, lit only by ambient light
+        let mut w = World::empty();
+        w.lights
+            .push(PointLight::new(pt(0.0, 10.0, -10.0), white()).into());
+        let material = Material::new().ambient(1.0).diffuse(0.0).specular(0.0);
+        w.objects
+            .push(Plane::new().material(material.clone()).into());
+        w.objects.push(
+            Plane::new()
+                .transform(Matrix4x4::translation(0.0, 0.0, 1.0) * Matrix4x4::rotation_x(PI / 2.0))
+                .material(material)
+                .into(),
+        );
+        assert_eq!(w.ambient_occlusion, None);
+        let comps_at = |w: &World, z: f64| {
+            let r = Ray::new(pt(0.0, 1.0, z), v(0.0, -1.0, 0.0));
+            let xs = w.intersect(&r);
+            let comps = xs[0].prepare_computations(&r, &xs);
+            (
+                w.shade_hit(&comps, 4),
+                w.unoccluded(&comps, &AmbientOcclusion::default()),
+            )
+        };
+
+        // without ambient occlusion the ambient light is flat
+        assert_eq!(comps_at(&w, 0.9).0, white());
+
+        // the corner is darker than the open floor
+        w.ambient_occlusion = Some(AmbientOcclusion::new(64, 1.0));
+        let (corner, corner_unoccluded) = comps_at(&w, 0.9);
+        let (open, open_unoccluded) = comps_at(&w, -5.0);
+        assert!(corner_unoccluded > 0.0 && corner_unoccluded < 1.0);
+        assert_eq!(open_unoccluded, 1.0);
+        assert!(corner.red < 1.0);
+        assert_eq!(open, white());
+
+        // the wall no longer occludes once it is further than the distance
+        w.ambient_occlusion = Some(AmbientOcclusion::new(64, 0.05));
+        assert_eq!(comps_at(&w, 0.5).0, white());
+
+        // a standalone ambient occlusion canvas
+        let mut c = Camera::new(3, 1, PI / 2.0);
+        c.set_transform(view_transform(
+            pt(0.0, 0.5, -1.0),
+            pt(0.0, 0.5, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        w.objects.truncate(1);
+        let image = c.render_ambient_occlusion(&mut w);
+        // nothing occludes the sky
+        assert_eq!(image.pixel_at(1, 0), white());
+        let mut c = Camera::new(1, 1, 0.1);
+        c.set_transform(view_transform(
+            pt(0.0, 1.0, -2.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        w.objects.push(
+            Sphere::new()
+                .transform(
+                    Matrix4x4::translation(0.0, 0.5, 0.3) * Matrix4x4::scaling(0.2, 0.2, 0.2),
+                )
+                .into(),
+        );
+        w.ambient_occlusion = None;
+        let pixel = c.render_ambient_occlusion(&mut w).pixel_at(0, 0);
+        assert!(pixel.red < 1.0, "{:?}", pixel);
+        assert_eq!(pixel.red, pixel.green);
+    }
+
+    #[test]
+    fn cosine_weighted_hemisphere_samples() {
+        let mut rng = Rng::new(1);
+        for normal in &[v(0.0, 1.0, 0.0), v(1.0, 0.0, 0.0), v(0.0, 0.6, -0.8)] {
+            for _ in 0..100 {
+                let d = cosine_sample_hemisphere(*normal, rng.next_f64(), rng.next_f64());
+                assert!(d.is_vector());
+                assert!((d.magnitude() - 1.0).abs() < EPSILON);
+                assert!(d.dot(normal) >= 0.0);
+            }
+        }
+        // the center of the square maps to the normal
+        let d = cosine_sample_hemisphere(v(0.0, 1.0, 0.0), 0.0, 0.0);
+        assert_eq!(d, v(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn camera_render_parallel() {
+        // the world and its shapes can be shared between threads
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+        assert_send_sync::<Box<dyn Shape>>();
+
+        // rendering in tiles matches rendering one pixel at a time
+        let mut w = World::default();
+        w.objects.push(
+            Plane::new()
+                .transform(Matrix4x4::translation(0.0, -1.0, 0.0))
+                .material(Material::new().reflective(0.5))
+                .into(),
+        );
+        let mut c = Camera::new(TILE_SIZE * 2 + 5, TILE_SIZE + 3, PI / 2.0);
+        c.set_transform(view_transform(
+            pt(0.0, 1.0, -5.0),
+            pt(0.0, 0.0, 0.0),
+            v(0.0, 1.0, 0.0),
+        ));
+        let expected = c.render(&mut w);
+        for threads in 1..4 {
+            let image = c.render_parallel(&mut w, threads);
+            for y in 0..c.vsize {
+                for x in 0..c.hsize {
+                    assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+                }
+            }
+        }
+    }
+
     #[test]
     fn shadow_rays() {
         // there is no shadow when nothing is collinear with point and light
         let w = World::default();
         let p = pt(0.0, 10.0, 0.0);
-        assert_eq!(w.is_shadowed(&w.lights[0], p), false);
+        assert_eq!(w.is_shadowed(w.lights[0].position(), p, 0.0), false);
 
         // the shadow when an object is between the point and the light
         let p = pt(10.0, -10.0, 10.0);
-        assert_eq!(w.is_shadowed(&w.lights[0], p), true);
+        assert_eq!(w.is_shadowed(w.lights[0].position(), p, 0.0), true);
 
         // there is no shadow when an object is behind the light
         let p = pt(-20.0, 20.0, -20.0);
-        assert_eq!(w.is_shadowed(&w.lights[0], p), false);
+        assert_eq!(w.is_shadowed(w.lights[0].position(), p, 0.0), false);
 
         // there is no shadow when an object is behind the point
         let p = pt(-2.0, 2.0, -2.0);
-        assert_eq!(w.is_shadowed(&w.lights[0], p), false);
+        assert_eq!(w.is_shadowed(w.lights[0].position(), p, 0.0), false);
     }
 
     #[test]
     fn world_shade_hit_shadow() {
         // shade_hit() is given an intersection in shadow
         let mut w = World::empty();
-        w.lights.push(PointLight::new(
-            pt(0.0, 0.0, -10.0),
-            Color::new(1.0, 1.0, 1.0),
-        ));
+        w.lights
+            .push(PointLight::new(pt(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
         let s1 = Sphere::new();
         let transform = Matrix4x4::translation(0.0, 0.0, 10.0);
         let s2 = Sphere::new().transform(transform);
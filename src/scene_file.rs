@@ -0,0 +1,1431 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use yaml_rust::{Yaml, YamlLoader};
+
+/// The pattern types that can be given in a material's `pattern`.
+const PATTERN_TYPES: [&str; 17] = [
+    "solid",
+    "stripes",
+    "gradient",
+    "radial-gradient",
+    "ring",
+    "smooth-ring",
+    "checkers",
+    "blend",
+    "add",
+    "multiply",
+    "mask",
+    "perturbed",
+    "wood",
+    "marble",
+    "granite",
+    "map",
+    "test",
+];
+
+struct Definitions {
+    transforms: HashMap<String, Matrix4x4>,
+    materials: HashMap<String, Material>,
+    shapes: HashMap<String, Yaml>,
+    // where the files the scene refers to are found from, which is the
+    // working directory unless the scene was loaded from a file
+    directory: PathBuf,
+}
+
+impl Definitions {
+    fn new() -> Definitions {
+        Definitions {
+            transforms: HashMap::new(),
+            materials: HashMap::new(),
+            shapes: HashMap::new(),
+            directory: PathBuf::new(),
+        }
+    }
+
+    fn relative_to(directory: &Path) -> Definitions {
+        Definitions {
+            directory: directory.to_owned(),
+            ..Definitions::new()
+        }
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.directory.join(file)
+    }
+
+    fn define(&mut self, obj: &Yaml) -> Result<()> {
+        let name = obj["define"].as_string("define")?;
+        let value = &obj["value"];
+        // an explicit kind wins; otherwise infer it from the value's structure
+        let kind = match (&obj["kind"], value) {
+            (Yaml::String(kind), _) => kind.as_str(),
+            (Yaml::BadValue, Yaml::Hash(_)) if !value["add"].is_badvalue() => "shape",
+            (Yaml::BadValue, Yaml::Hash(_)) => "material",
+            (Yaml::BadValue, Yaml::Array(_)) => "transform",
+            (Yaml::BadValue, _) => return Err(anyhow!("Unable to infer the kind of {}", name)),
+            (kind, _) => return Err(anyhow!("kind is not a String: {:?}", kind)),
+        };
+        match kind {
+            "shape" => {
+                self.shapes.insert(name.to_owned(), value.clone());
+            }
+            "material" => {
+                let mut base = Material::new();
+                if let Some(extend) = obj["extend"].as_str() {
+                    base = self.material(extend)?;
+                }
+                self.materials
+                    .insert(name.to_owned(), value.populate_material(base, self)?);
+            }
+            "transform" => {
+                self.transforms
+                    .insert(name.to_owned(), value.as_transform(self)?);
+            }
+            _ => return Err(anyhow!("Unexpected define kind: {}", kind)),
+        }
+        Ok(())
+    }
+
+    fn transform(&self, name: &str) -> Result<Matrix4x4> {
+        self.transforms
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown transform: {}", name))
+    }
+
+    fn material(&self, name: &str) -> Result<Material> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown material: {}", name))
+    }
+}
+
+trait YamlExt {
+    fn as_string(&self, what: &str) -> Result<&str>;
+    fn as_boolean(&self, what: &str) -> Result<bool>;
+    fn entries(&self, what: &str) -> Result<Vec<(&str, &Yaml)>>;
+    fn as_float(&self) -> Result<f64>;
+    fn as_v(&self) -> Result<Tuple>;
+    fn as_pt(&self) -> Result<Tuple>;
+    fn as_color(&self) -> Result<Color>;
+    fn as_camera(&self) -> Result<Camera>;
+    fn as_light(&self) -> Result<Light>;
+    fn as_ambient_occlusion(&self) -> Result<AmbientOcclusion>;
+    fn as_transform(&self, defs: &Definitions) -> Result<Matrix4x4>;
+    fn populate_transform(&self, transform: Matrix4x4) -> Result<Matrix4x4>;
+    fn as_material(&self, defs: &Definitions) -> Result<Material>;
+    fn populate_material(&self, material: Material, defs: &Definitions) -> Result<Material>;
+    fn as_shape(&self, defs: &Definitions) -> Result<Box<dyn Shape>>;
+    fn populate_shape(&self, shape: Box<dyn Shape>, defs: &Definitions) -> Result<Box<dyn Shape>>;
+    fn as_pattern(&self, defs: &Definitions) -> Result<Pattern>;
+    fn as_sub_pattern(&self, defs: &Definitions) -> Result<Pattern>;
+    fn pattern_props(&self, defs: &Definitions) -> Result<(Pattern, Pattern, Matrix4x4)>;
+    fn as_uv_pattern(&self, defs: &Definitions) -> Result<UvPattern>;
+    fn as_texture_map(&self, defs: &Definitions) -> Result<Pattern>;
+    fn as_solid_texture(&self, defs: &Definitions) -> Result<Pattern>;
+    fn as_bump(&self, defs: &Definitions) -> Result<Bump>;
+}
+
+impl YamlExt for Yaml {
+    fn as_float(&self) -> Result<f64> {
+        match &self {
+            Yaml::Real(_) => self
+                .as_f64()
+                .ok_or_else(|| anyhow!("Unable to read f64 from {:?}", self)),
+            Yaml::Integer(int) => Ok(*int as f64),
+            _ => Err(anyhow!("Unable to read f64 from {:?}", self)),
+        }
+    }
+
+    fn as_string(&self, what: &str) -> Result<&str> {
+        self.as_str()
+            .ok_or_else(|| anyhow!("{} is not a String: {:?}", what, self))
+    }
+
+    fn as_boolean(&self, what: &str) -> Result<bool> {
+        self.as_bool()
+            .ok_or_else(|| anyhow!("{} is not a Bool: {:?}", what, self))
+    }
+
+    // the key / value pairs of a hash with string keys
+    fn entries(&self, what: &str) -> Result<Vec<(&str, &Yaml)>> {
+        let hash = self
+            .as_hash()
+            .ok_or_else(|| anyhow!("{} are not a Hash", what))?;
+        hash.iter()
+            .map(|(key, value)| Ok((key.as_string(&format!("{} key", what))?, value)))
+            .collect()
+    }
+
+    fn as_v(&self) -> Result<Tuple> {
+        Ok(v(
+            self[0].as_float()?,
+            self[1].as_float()?,
+            self[2].as_float()?,
+        ))
+    }
+
+    fn as_pt(&self) -> Result<Tuple> {
+        Ok(pt(
+            self[0].as_float()?,
+            self[1].as_float()?,
+            self[2].as_float()?,
+        ))
+    }
+
+    fn as_color(&self) -> Result<Color> {
+        Ok(Color::new(
+            self[0].as_float()?,
+            self[1].as_float()?,
+            self[2].as_float()?,
+        ))
+    }
+
+    fn as_camera(&self) -> Result<Camera> {
+        let mut width = None;
+        let mut height = None;
+        let mut field_of_view = None;
+        let mut from = None;
+        let mut to = None;
+        let mut up = None;
+        let mut samples = None;
+        let mut jitter = false;
+        let mut adaptive_threshold = None;
+        let mut aperture = 0.0;
+        let mut focal_distance = None;
+        let mut lens_samples = None;
+        let mut time_samples = None;
+        for (key, value) in self.entries("camera properties")? {
+            match key {
+                "add" => {}
+                "width" => width = Some(value.as_float()? as usize),
+                "height" => height = Some(value.as_float()? as usize),
+                "field-of-view" => field_of_view = Some(value.as_float()?),
+                "from" => from = Some(value.as_v()?),
+                "to" => to = Some(value.as_v()?),
+                "up" => up = Some(value.as_v()?),
+                "samples" => samples = Some(value.as_float()? as usize),
+                "jitter" => jitter = value.as_boolean("camera jitter")?,
+                "adaptive-threshold" => adaptive_threshold = Some(value.as_float()?),
+                "aperture" => aperture = value.as_float()?,
+                "focal-distance" => focal_distance = Some(value.as_float()?),
+                "lens-samples" => lens_samples = Some(value.as_float()? as usize),
+                "time-samples" => time_samples = Some(value.as_float()? as usize),
+                _ => return Err(anyhow!("Unexpected camera property: {}", key)),
+            }
+        }
+        let missing = |field| anyhow!("camera missing required field '{}'", field);
+        let mut camera = Camera::new(
+            width.ok_or_else(|| missing("width"))?,
+            height.ok_or_else(|| missing("height"))?,
+            field_of_view.ok_or_else(|| missing("field-of-view"))?,
+        );
+        let from = from.ok_or_else(|| missing("from"))?;
+        let to = to.ok_or_else(|| missing("to"))?;
+        camera.set_transform(view_transform(from, to, up.ok_or_else(|| missing("up"))?));
+        // without a focal distance, focus on the point the camera looks at
+        camera.aperture = aperture;
+        camera.focal_distance = focal_distance.unwrap_or_else(|| (to - from).magnitude());
+        if let Some(lens_samples) = lens_samples {
+            camera = camera.lens_samples(lens_samples);
+        }
+        if let Some(time_samples) = time_samples {
+            camera = camera.time_samples(time_samples);
+        }
+        // samples is the number of rays along each side of a pixel. When
+        // sampling adaptively it is the most a pixel will be subdivided to.
+        camera.sampling = match (samples, adaptive_threshold) {
+            (samples, Some(threshold)) => Sampling::Adaptive {
+                threshold,
+                depth: (samples.unwrap_or(4).max(1) as f64).log2().ceil() as usize,
+            },
+            (Some(n), None) if n > 1 && jitter => Sampling::Jittered(n),
+            (Some(n), None) if n > 1 => Sampling::Grid(n),
+            _ => Sampling::Center,
+        };
+        Ok(camera)
+    }
+
+    fn as_light(&self) -> Result<Light> {
+        let mut r#type = None;
+        let mut position = None;
+        let mut intensity = None;
+        let mut corner = None;
+        let mut uvec = None;
+        let mut vvec = None;
+        let mut usteps = 1;
+        let mut vsteps = 1;
+        let mut jitter = false;
+        let mut direction = None;
+        let mut inner_angle = None;
+        let mut outer_angle = None;
+        let mut angular_diameter = 0.0;
+        let mut samples = None;
+        for (key, value) in self.entries("light properties")? {
+            match key {
+                "add" => {}
+                "type" => r#type = Some(value.as_string("light type")?),
+                "at" => position = Some(value.as_pt()?),
+                "intensity" => intensity = Some(value.as_color()?),
+                "corner" => corner = Some(value.as_pt()?),
+                "uvec" => uvec = Some(value.as_v()?),
+                "vvec" => vvec = Some(value.as_v()?),
+                "usteps" => usteps = value.as_float()? as usize,
+                "vsteps" => vsteps = value.as_float()? as usize,
+                "jitter" => jitter = value.as_boolean("light jitter")?,
+                "direction" => direction = Some(value.as_v()?),
+                "inner-angle" => inner_angle = Some(value.as_float()?),
+                "outer-angle" => outer_angle = Some(value.as_float()?),
+                "angular-diameter" => angular_diameter = value.as_float()?,
+                "samples" => samples = Some(value.as_float()? as usize),
+                _ => return Err(anyhow!("Unexpected light property: {}", key)),
+            }
+        }
+        let intensity = intensity.unwrap_or_else(|| Color::new(1.0, 1.0, 1.0));
+        let position = position.unwrap_or_else(|| pt(0.0, 0.0, 0.0));
+        let r#type = match r#type {
+            Some(r#type) => r#type,
+            None if corner.is_some() => "area",
+            None => "point",
+        };
+        match r#type {
+            "point" => Ok(PointLight::new(position, intensity).into()),
+            "area" => {
+                if usteps == 0 || vsteps == 0 {
+                    return Err(anyhow!("area light usteps and vsteps must be at least 1"));
+                }
+                let light = AreaLight::new(
+                    corner.ok_or_else(|| anyhow!("area light missing required field 'corner'"))?,
+                    uvec.ok_or_else(|| anyhow!("area light missing required field 'uvec'"))?,
+                    usteps,
+                    vvec.ok_or_else(|| anyhow!("area light missing required field 'vvec'"))?,
+                    vsteps,
+                    intensity,
+                )
+                .jitter(jitter);
+                Ok(light.into())
+            }
+            "spot" => {
+                let direction = direction
+                    .ok_or_else(|| anyhow!("spot light missing required field 'direction'"))?;
+                let outer_angle = outer_angle
+                    .ok_or_else(|| anyhow!("spot light missing required field 'outer-angle'"))?;
+                let inner_angle = inner_angle.unwrap_or(outer_angle);
+                let light =
+                    SpotLight::new(position, direction, inner_angle, outer_angle, intensity);
+                Ok(light.into())
+            }
+            "directional" => {
+                let direction = direction.ok_or_else(|| {
+                    anyhow!("directional light missing required field 'direction'")
+                })?;
+                let mut light =
+                    DirectionalLight::new(direction, intensity).angular_diameter(angular_diameter);
+                if let Some(samples) = samples {
+                    light = light.samples(samples);
+                }
+                Ok(light.into())
+            }
+            _ => Err(anyhow!("Unexpected light type: {}", r#type)),
+        }
+    }
+
+    fn as_ambient_occlusion(&self) -> Result<AmbientOcclusion> {
+        let mut settings = AmbientOcclusion::default();
+        for (key, value) in self.entries("ambient occlusion properties")? {
+            match key {
+                "add" => {}
+                "samples" => settings.samples = (value.as_float()? as usize).max(1),
+                "distance" => settings.distance = value.as_float()?,
+                _ => return Err(anyhow!("Unexpected ambient occlusion property: {}", key)),
+            }
+        }
+        Ok(settings)
+    }
+
+    fn as_transform(&self, defs: &Definitions) -> Result<Matrix4x4> {
+        let mut transform = id();
+        let transforms = self
+            .as_vec()
+            .ok_or_else(|| anyhow!("transforms should be an Array"))?;
+        for params in transforms {
+            match &params {
+                Yaml::Array(_) => transform = params.populate_transform(transform)?,
+                Yaml::String(name) => transform = defs.transform(name)?,
+                _ => return Err(anyhow!("Unexpected transform: {:?}", params)),
+            }
+        }
+        Ok(transform)
+    }
+
+    fn populate_transform(&self, transform: Matrix4x4) -> Result<Matrix4x4> {
+        let mut transform = transform;
+        let transform_type = self[0].as_string("transform type")?;
+        match transform_type {
+            "rotate-x" => {
+                transform = transform.rotate_x(self[1].as_float()?);
+            }
+            "rotate-y" => {
+                transform = transform.rotate_y(self[1].as_float()?);
+            }
+            "rotate-z" => {
+                transform = transform.rotate_z(self[1].as_float()?);
+            }
+            "scale" => {
+                transform = transform.scale(
+                    self[1].as_float()?,
+                    self[2].as_float()?,
+                    self[3].as_float()?,
+                );
+            }
+            "translate" => {
+                transform = transform.translate(
+                    self[1].as_float()?,
+                    self[2].as_float()?,
+                    self[3].as_float()?,
+                );
+            }
+            _ => {
+                return Err(anyhow!("Unrecognized transform: {}", transform_type));
+            }
+        }
+        Ok(transform)
+    }
+
+    fn as_material(&self, defs: &Definitions) -> Result<Material> {
+        self.populate_material(Material::new(), defs)
+    }
+
+    fn populate_material(&self, material: Material, defs: &Definitions) -> Result<Material> {
+        let mut material = material;
+        for (key, value) in self.entries("material properties")? {
+            match key {
+                "pattern" => material.pattern = Some(value.as_pattern(defs)?),
+                "color" => material.color = value.as_color()?,
+                "ambient" => material.ambient = value.as_float()?,
+                "diffuse" => material.diffuse = value.as_float()?,
+                "specular" => material.specular = value.as_float()?,
+                "reflective" => material.reflective = value.as_float()?,
+                "shininess" => material.shininess = value.as_float()?,
+                "transparency" => material.transparency = value.as_float()?,
+                "refractive-index" => material.refractive_index = value.as_float()?,
+                "emissive" => material.emissive = value.as_color()?,
+                "bump" => material.bump = Some(value.as_bump(defs)?),
+                "normal-map" => material.normal_map = Some(value.as_uv_pattern(defs)?),
+                _ => {
+                    return Err(anyhow!("Unknown material property: {}", key));
+                }
+            }
+        }
+        Ok(material)
+    }
+
+    fn as_shape(&self, defs: &Definitions) -> Result<Box<dyn Shape>> {
+        let r#type = self["add"].as_string("add parameter")?;
+        let shape = match r#type {
+            "cube" => {
+                let mut shape = Cube::new().shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            "plane" => {
+                let mut shape = Plane::new().shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            "sphere" => {
+                let mut shape = Sphere::new().shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            "cylinder" => {
+                let min = self["min"].as_float()?;
+                let max = self["max"].as_float()?;
+                let closed = self["closed"].as_boolean("closed property")?;
+                let mut shape = Cylinder::new(min, max, closed).shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            "cone" => {
+                let min = self["min"].as_float()?;
+                let max = self["max"].as_float()?;
+                let closed = self["closed"].as_boolean("closed property")?;
+                let mut shape = Cone::new(min, max, closed).shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            "torus" => {
+                let mut major = 1.0;
+                let mut minor = 0.25;
+                if !self["major-radius"].is_badvalue() {
+                    major = self["major-radius"].as_float()?;
+                }
+                if !self["minor-radius"].is_badvalue() {
+                    minor = self["minor-radius"].as_float()?;
+                }
+                let mut shape = Torus::new(major, minor).shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            "group" => {
+                let mut group = Group::new();
+                let children = self["children"]
+                    .as_vec()
+                    .ok_or_else(|| anyhow!("children property is not an Array"))?;
+                for child_obj in children {
+                    let child = child_obj.as_shape(defs)?;
+                    group.add_child(child);
+                }
+                let mut shape = group.shape();
+                shape = self.populate_shape(shape, defs)?;
+                shape
+            }
+            name => {
+                if let Some(def) = defs.shapes.get(name) {
+                    let mut shape = def.clone().as_shape(defs)?;
+                    shape = self.populate_shape(shape, defs)?;
+                    shape
+                } else {
+                    return Err(anyhow!("Unknown shape: {}", name));
+                }
+            }
+        };
+        Ok(shape)
+    }
+
+    fn populate_shape(&self, shape: Box<dyn Shape>, defs: &Definitions) -> Result<Box<dyn Shape>> {
+        let mut shape = shape;
+        match &self["transform"] {
+            Yaml::Array(_) => shape.set_transform(self["transform"].as_transform(defs)?),
+            Yaml::String(name) => shape.set_transform(defs.transform(name)?),
+            _ => {}
+        }
+        // where a moving shape ends up by the time the shutter closes
+        match &self["transform-end"] {
+            Yaml::Array(_) => {
+                shape.set_transform_end(Some(self["transform-end"].as_transform(defs)?))
+            }
+            Yaml::String(name) => shape.set_transform_end(Some(defs.transform(name)?)),
+            _ => {}
+        }
+        match &self["material"] {
+            Yaml::Hash(_) => shape.set_material(self["material"].as_material(defs)?),
+            Yaml::String(name) => shape.set_material(defs.material(name)?),
+            _ => {}
+        }
+        if let Some(shadow) = self["shadow"].as_bool() {
+            shape.set_shadow(shadow);
+        }
+        Ok(shape)
+    }
+
+    fn as_uv_pattern(&self, defs: &Definitions) -> Result<UvPattern> {
+        let r#type = self["type"]
+            .as_str()
+            .ok_or_else(|| anyhow!("uv_pattern type is not a String"))?;
+        match r#type {
+            "checkers" => Ok(uv_checkers(
+                self["width"].as_float()?,
+                self["height"].as_float()?,
+                self["colors"][0].as_color()?,
+                self["colors"][1].as_color()?,
+            )),
+            "align_check" => {
+                let colors = &self["colors"];
+                Ok(uv_align_check(
+                    colors["main"].as_color()?,
+                    colors["ul"].as_color()?,
+                    colors["ur"].as_color()?,
+                    colors["bl"].as_color()?,
+                    colors["br"].as_color()?,
+                ))
+            }
+            "image" => {
+                let file = self["file"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("image uv_pattern missing required field 'file'"))?;
+                let path = defs.path(file);
+                let canvas = Canvas::load(&path)
+                    .map_err(|e| anyhow!("Unable to load {}: {}", path.display(), e))?;
+                let mut image = UvImage::new(canvas);
+                match self["filter"].as_str() {
+                    Some("nearest") | None => {}
+                    Some("bilinear") => image = image.filter(TextureFilter::Bilinear),
+                    Some(filter) => return Err(anyhow!("Unexpected image filter: {}", filter)),
+                }
+                match self["wrap"].as_str() {
+                    Some("repeat") | None => {}
+                    Some("clamp") => image = image.wrap(TextureWrap::Clamp),
+                    Some(wrap) => return Err(anyhow!("Unexpected image wrap: {}", wrap)),
+                }
+                Ok(UvPattern::Image(image))
+            }
+            _ => Err(anyhow!("Unexpected uv_pattern type: {}", r#type)),
+        }
+    }
+
+    fn as_texture_map(&self, defs: &Definitions) -> Result<Pattern> {
+        let mapping = self["mapping"]
+            .as_str()
+            .ok_or_else(|| anyhow!("map pattern missing required field 'mapping'"))?;
+        let mut pattern = match mapping {
+            "spherical" => texture_map(
+                UvMapping::Spherical,
+                self["uv_pattern"].as_uv_pattern(defs)?,
+            ),
+            "planar" => texture_map(UvMapping::Planar, self["uv_pattern"].as_uv_pattern(defs)?),
+            "cylindrical" => texture_map(
+                UvMapping::Cylindrical,
+                self["uv_pattern"].as_uv_pattern(defs)?,
+            ),
+            "cube" => {
+                if let Yaml::Hash(_) = self["uv_pattern"] {
+                    texture_map(UvMapping::Cube, self["uv_pattern"].as_uv_pattern(defs)?)
+                } else {
+                    cube_map(
+                        self["left"].as_uv_pattern(defs)?,
+                        self["front"].as_uv_pattern(defs)?,
+                        self["right"].as_uv_pattern(defs)?,
+                        self["back"].as_uv_pattern(defs)?,
+                        self["up"].as_uv_pattern(defs)?,
+                        self["down"].as_uv_pattern(defs)?,
+                    )
+                }
+            }
+            _ => return Err(anyhow!("Unexpected mapping: {}", mapping)),
+        };
+        if let Yaml::Array(_) = self["transform"] {
+            pattern.set_transform(self["transform"].as_transform(defs)?);
+        }
+        Ok(pattern)
+    }
+
+    fn as_pattern(&self, defs: &Definitions) -> Result<Pattern> {
+        let r#type = self["type"].as_string("pattern type")?;
+        // most patterns are made from two others, given as `colors` or
+        // `patterns`
+        let pattern: fn(Pattern, Pattern) -> Pattern = match r#type {
+            "stripes" => |a, b| stripe_pattern(a, b),
+            "gradient" => |a, b| gradient_pattern(a, b),
+            "radial-gradient" => |a, b| radial_gradient_pattern(a, b),
+            "ring" => |a, b| ring_pattern(a, b),
+            "smooth-ring" => |a, b| smooth_ring_pattern(a, b),
+            "checkers" => |a, b| checkers_pattern(a, b),
+            "blend" => |a, b| blend_pattern(a, b),
+            "add" => |a, b| add_pattern(a, b),
+            "multiply" => |a, b| multiply_pattern(a, b),
+            "mask" => {
+                let (a, b, transform) = self.pattern_props(defs)?;
+                let mask = self["mask"]
+                    .as_sub_pattern(defs)
+                    .context("mask pattern missing required field 'mask'")?;
+                let mut pattern = mask_pattern(mask, a, b);
+                pattern.set_transform(transform);
+                return Ok(pattern);
+            }
+            "solid" => {
+                for (key, _) in self.entries("pattern properties")? {
+                    if key != "type" && key != "color" {
+                        return Err(anyhow!("Unexpected pattern property: {}", key));
+                    }
+                }
+                return Ok(solid_pattern(self["color"].as_color()?));
+            }
+            "test" => {
+                let mut pattern = test_pattern();
+                for (key, value) in self.entries("pattern properties")? {
+                    match key {
+                        "type" => {}
+                        "transform" => pattern.set_transform(value.as_transform(defs)?),
+                        _ => return Err(anyhow!("Unexpected pattern property: {}", key)),
+                    }
+                }
+                return Ok(pattern);
+            }
+            "map" => return self.as_texture_map(defs),
+            "wood" | "marble" | "granite" => return self.as_solid_texture(defs),
+            "perturbed" => {
+                let mut inner = None;
+                let mut noise = Noise::new(0.2);
+                let mut transform = Matrix4x4::default();
+                for (key, value) in self.entries("pattern properties")? {
+                    match key {
+                        "type" => {}
+                        "pattern" => inner = Some(value.as_pattern(defs)?),
+                        "scale" => noise.scale = value.as_float()?,
+                        "octaves" => noise = noise.octaves(value.as_float()? as usize),
+                        "persistence" => noise.persistence = value.as_float()?,
+                        "transform" => transform = value.as_transform(defs)?,
+                        _ => return Err(anyhow!("Unexpected pattern property: {}", key)),
+                    }
+                }
+                let inner = inner
+                    .ok_or_else(|| anyhow!("perturbed pattern missing required field 'pattern'"))?;
+                let mut pattern = perturbed_pattern(inner, noise);
+                pattern.set_transform(transform);
+                return Ok(pattern);
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Unexpected pattern type: {}, expected one of: {}",
+                    r#type,
+                    PATTERN_TYPES.join(", ")
+                ))
+            }
+        };
+        let (a, b, transform) = self.pattern_props(defs)?;
+        let mut pattern = pattern(a, b);
+        pattern.set_transform(transform);
+        Ok(pattern)
+    }
+
+    // a pattern nested in another, given either as a pattern or as a color
+    fn as_sub_pattern(&self, defs: &Definitions) -> Result<Pattern> {
+        match self {
+            Yaml::Hash(_) => self.as_pattern(defs),
+            _ => Ok(self.as_color()?.into()),
+        }
+    }
+
+    // wood, marble and granite: a color ramp looked up by noise
+    fn as_solid_texture(&self, defs: &Definitions) -> Result<Pattern> {
+        let r#type = self["type"].as_string("pattern type")?;
+        let mut colors = Vec::new();
+        let mut stops = None;
+        // how far the noise bends the pattern, unless the scene says otherwise
+        let mut noise = match r#type {
+            "wood" => Noise::new(0.3).octaves(2),
+            "marble" => Noise::new(1.5).octaves(4),
+            _ => Noise::new(1.0).octaves(6),
+        };
+        let mut transform = Matrix4x4::default();
+        for (key, value) in self.entries("pattern properties")? {
+            match key {
+                "type" => {}
+                "colors" => {
+                    colors = value
+                        .as_vec()
+                        .ok_or_else(|| anyhow!("{} colors are not an Array", r#type))?
+                        .iter()
+                        .map(|color| color.as_color())
+                        .collect::<Result<_>>()?
+                }
+                // where each color sits on the ramp, evenly spaced if missing
+                "stops" => {
+                    stops = Some(
+                        value
+                            .as_vec()
+                            .ok_or_else(|| anyhow!("{} stops are not an Array", r#type))?
+                            .iter()
+                            .map(|stop| stop.as_float())
+                            .collect::<Result<Vec<_>>>()?,
+                    )
+                }
+                "scale" => noise.scale = value.as_float()?,
+                "octaves" => noise = noise.octaves(value.as_float()? as usize),
+                "persistence" => noise.persistence = value.as_float()?,
+                "transform" => transform = value.as_transform(defs)?,
+                _ => return Err(anyhow!("Unexpected pattern property: {}", key)),
+            }
+        }
+        if colors.is_empty() {
+            return Err(anyhow!(
+                "{} pattern missing required field 'colors'",
+                r#type
+            ));
+        }
+        let ramp = match stops {
+            Some(stops) if stops.len() == colors.len() => {
+                ColorRamp::new(stops.into_iter().zip(colors).collect())
+            }
+            Some(_) => return Err(anyhow!("{} pattern needs a stop for each color", r#type)),
+            None => ColorRamp::even(colors),
+        };
+        let mut pattern = match r#type {
+            "wood" => wood_pattern(ramp, noise),
+            "marble" => marble_pattern(ramp, noise),
+            _ => granite_pattern(ramp, noise),
+        };
+        pattern.set_transform(transform);
+        Ok(pattern)
+    }
+
+    fn as_bump(&self, defs: &Definitions) -> Result<Bump> {
+        let r#type = self["type"].as_string("bump type")?;
+        let mut amplitude = 0.1;
+        let mut noise = Noise::new(0.1).octaves(3);
+        let mut mapping = None;
+        let mut uv_pattern = None;
+        let mut transform = Matrix4x4::default();
+        for (key, value) in self.entries("bump properties")? {
+            match key {
+                "type" => {}
+                // how high the waves or the white of a height map are; noise
+                // bumps are as high as their scale instead
+                "amplitude" | "depth" if r#type == "noise" => {
+                    return Err(anyhow!(
+                        "Unexpected bump property: {}, noise bumps use scale",
+                        key
+                    ))
+                }
+                "amplitude" | "depth" => amplitude = value.as_float()?,
+                "scale" => noise.scale = value.as_float()?,
+                "octaves" => noise = noise.octaves(value.as_float()? as usize),
+                "persistence" => noise.persistence = value.as_float()?,
+                "mapping" => {
+                    mapping = Some(match value.as_string("bump mapping")? {
+                        "spherical" => UvMapping::Spherical,
+                        "planar" => UvMapping::Planar,
+                        "cylindrical" => UvMapping::Cylindrical,
+                        "cube" => UvMapping::Cube,
+                        mapping => return Err(anyhow!("Unexpected bump mapping: {}", mapping)),
+                    })
+                }
+                "uv_pattern" => uv_pattern = Some(value.as_uv_pattern(defs)?),
+                "transform" => transform = value.as_transform(defs)?,
+                _ => return Err(anyhow!("Unexpected bump property: {}", key)),
+            }
+        }
+        let mut bump = match r#type {
+            "sine" => sine_bump(amplitude),
+            "noise" => noise_bump(noise),
+            "height-map" => height_map(
+                mapping
+                    .ok_or_else(|| anyhow!("height-map bump missing required field 'mapping'"))?,
+                uv_pattern.ok_or_else(|| {
+                    anyhow!("height-map bump missing required field 'uv_pattern'")
+                })?,
+                amplitude,
+            ),
+            _ => {
+                return Err(anyhow!(
+                    "Unexpected bump type: {}, expected one of: sine, noise, height-map",
+                    r#type
+                ))
+            }
+        };
+        bump.set_transform(transform);
+        Ok(bump)
+    }
+
+    fn pattern_props(&self, defs: &Definitions) -> Result<(Pattern, Pattern, Matrix4x4)> {
+        let mut a = Color::default().into();
+        let mut b = Color::default().into();
+        let mut transform = Matrix4x4::default();
+        for (key, value) in self.entries("pattern properties")? {
+            match key {
+                "type" | "mask" => {}
+                // each of the two may be a color or another pattern
+                "colors" | "patterns" => {
+                    a = value[0].as_sub_pattern(defs)?;
+                    b = value[1].as_sub_pattern(defs)?;
+                }
+                "transform" => {
+                    transform = value.as_transform(defs)?;
+                }
+                _ => return Err(anyhow!("Unexpected pattern property: {}", key)),
+            }
+        }
+        Ok((a, b, transform))
+    }
+}
+
+/// Reads the YAML scene description at `path` into a world and the camera viewing it.
+/// Files the scene refers to, such as images, are found relative to it.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<(World, Camera)> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read {}", path.display()))?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse(&contents, Definitions::relative_to(directory))
+        .with_context(|| format!("Unable to load {}", path.display()))
+}
+
+/// Parses a YAML scene description into a world and the camera viewing it.
+/// Files the scene refers to are found relative to the working directory.
+pub fn from_str(contents: &str) -> Result<(World, Camera)> {
+    parse(contents, Definitions::new())
+}
+
+fn parse(contents: &str, mut definitions: Definitions) -> Result<(World, Camera)> {
+    let yaml = YamlLoader::load_from_str(contents)?;
+    let items = yaml
+        .first()
+        .and_then(|doc| doc.as_vec())
+        .ok_or_else(|| anyhow!("scene file should be an Array of items"))?;
+    let mut world = World::empty();
+    let mut camera = None;
+    for (index, obj) in items.iter().enumerate() {
+        let describe = || match (&obj["add"], &obj["define"]) {
+            (Yaml::String(r#type), _) => format!("item {} (add: {})", index, r#type),
+            (_, Yaml::String(name)) => format!("item {} (define: {})", index, name),
+            _ => format!("item {}", index),
+        };
+        if let Yaml::String(r#type) = &obj["add"] {
+            match r#type.as_str() {
+                "camera" => camera = Some(obj.as_camera().with_context(describe)?),
+                "light" => world.lights.push(obj.as_light().with_context(describe)?),
+                "ambient-occlusion" => {
+                    world.ambient_occlusion =
+                        Some(obj.as_ambient_occlusion().with_context(describe)?)
+                }
+                _ => world
+                    .objects
+                    .push(obj.as_shape(&definitions).with_context(describe)?),
+            }
+        } else if let Yaml::String(_) = &obj["define"] {
+            definitions.define(obj).with_context(describe)?;
+        } else {
+            return Err(anyhow!("Unexpected object type: {:?}", obj)).with_context(describe);
+        }
+    }
+    let camera = camera.ok_or_else(|| anyhow!("scene file does not add a camera"))?;
+    Ok((world, camera))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [0, 1.5, -5]
+  to: [0, 1, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- define: white-material
+  value:
+    color: [1, 1, 1]
+    diffuse: 0.7
+- define: standard-transform
+  value:
+    - [translate, 1, -1, 1]
+    - [scale, 0.5, 0.5, 0.5]
+- add: sphere
+  material: white-material
+  transform: standard-transform
+- add: plane
+";
+
+    #[test]
+    fn loading_a_scene_from_a_string() {
+        let (world, camera) = from_str(SCENE).unwrap();
+        assert_eq!(camera.hsize, 100);
+        assert_eq!(camera.vsize, 50);
+        assert_eq!(world.lights.len(), 1);
+        assert_eq!(world.objects.len(), 2);
+        let sphere = &world.objects[0];
+        assert_eq!(sphere.material().color, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(sphere.material().diffuse, 0.7);
+        assert_eq!(
+            *sphere.transform(),
+            Matrix4x4::scaling(0.5, 0.5, 0.5) * Matrix4x4::translation(1.0, -1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn loading_a_scene_from_a_file() {
+        // images are found next to the scene, wherever it is run from
+        let directory = std::env::temp_dir().join(format!("scene-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("textures")).unwrap();
+        let mut canvas = Canvas::new(1, 1);
+        canvas.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        std::fs::write(directory.join("textures/red.ppm"), canvas.to_ppm()).unwrap();
+        let scene = format!(
+            "{}
+  material:
+    pattern:
+      type: map
+      mapping: spherical
+      uv_pattern:
+        type: image
+        file: textures/red.ppm
+",
+            SCENE
+        );
+        let path = directory.join("scene.yaml");
+        std::fs::write(&path, &scene).unwrap();
+        let loaded = load(&path);
+        let from_working_directory = from_str(&scene);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let (world, _) = loaded.unwrap();
+        let plane = &world.objects[1];
+        let color = plane.material().color_at(&**plane, pt(0.0, 0.0, 0.0), 0.0);
+        assert_eq!(color, Color::new(1.0, 0.0, 0.0));
+        assert!(from_working_directory.is_err());
+
+        // which is how the scenes that come with the ray tracer find theirs
+        assert!(load("scenes/image_texture.yaml").is_ok());
+    }
+
+    #[test]
+    fn definition_kinds_are_inferred_from_their_values() {
+        let scene = "
+- define: chalk
+  value:
+    color: [1, 1, 1]
+- define: shiny-chalk
+  extend: chalk
+  value:
+    reflective: 0.5
+- define: lift
+  value:
+    - [translate, 0, 1, 0]
+- define: ball
+  value:
+    add: sphere
+    material: shiny-chalk
+    transform: lift
+- define: nudge
+  kind: transform
+  value: [lift, [scale, 2, 2, 2]]
+";
+        let yaml = YamlLoader::load_from_str(scene).unwrap();
+        let mut defs = Definitions::new();
+        for obj in yaml[0].as_vec().unwrap() {
+            defs.define(obj).unwrap();
+        }
+        let shiny = defs.material("shiny-chalk").unwrap();
+        assert_eq!(shiny.color, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(shiny.reflective, 0.5);
+        assert_eq!(
+            defs.transform("lift").unwrap(),
+            Matrix4x4::translation(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            defs.transform("nudge").unwrap(),
+            Matrix4x4::scaling(2.0, 2.0, 2.0) * Matrix4x4::translation(0.0, 1.0, 0.0)
+        );
+        assert!(defs.shapes.contains_key("ball"));
+
+        // a shape added by its definition name
+        let add = YamlLoader::load_from_str("add: ball").unwrap();
+        let shape = add[0].as_shape(&defs).unwrap();
+        assert_eq!(shape.material().reflective, 0.5);
+
+        // an explicit kind that does not exist
+        let bad =
+            YamlLoader::load_from_str("define: thing\nkind: color\nvalue: [1, 1, 1]").unwrap();
+        assert!(defs.define(&bad[0]).is_err());
+    }
+
+    #[test]
+    fn loading_a_camera_with_depth_of_field() {
+        let scene = "
+add: camera
+width: 100
+height: 50
+field-of-view: 0.785
+from: [0, 0, -5]
+to: [0, 0, 0]
+up: [0, 1, 0]
+aperture: 0.2
+lens-samples: 32
+time-samples: 8
+";
+        let yaml = YamlLoader::load_from_str(scene).unwrap();
+        let camera = yaml[0].as_camera().unwrap();
+        assert_eq!(camera.aperture, 0.2);
+        assert_eq!(camera.focal_distance, 5.0);
+        assert_eq!(camera.lens_samples, 32);
+        assert_eq!(camera.time_samples, 8);
+
+        let yaml = YamlLoader::load_from_str(&format!("{}focal-distance: 2.5", scene)).unwrap();
+        let camera = yaml[0].as_camera().unwrap();
+        assert_eq!(camera.focal_distance, 2.5);
+    }
+
+    #[test]
+    fn loading_a_moving_shape() {
+        let scene = "
+- define: start
+  value:
+    - [translate, -1, 0, 0]
+- add: sphere
+  transform: start
+  transform-end:
+    - [translate, 1, 0, 0]
+";
+        let yaml = YamlLoader::load_from_str(scene).unwrap();
+        let mut defs = Definitions::new();
+        defs.define(&yaml[0][0]).unwrap();
+        let shape = yaml[0][1].as_shape(&defs).unwrap();
+        assert_eq!(*shape.transform(), Matrix4x4::translation(-1.0, 0.0, 0.0));
+        assert_eq!(
+            shape.common().transform_end(),
+            Some(&Matrix4x4::translation(1.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn loading_ambient_occlusion_settings() {
+        let scene = format!(
+            "{}- add: ambient-occlusion\n  samples: 32\n  distance: 0.5\n",
+            SCENE
+        );
+        let (world, _) = from_str(&scene).unwrap();
+        assert_eq!(
+            world.ambient_occlusion,
+            Some(AmbientOcclusion::new(32, 0.5))
+        );
+
+        let (world, _) = from_str(SCENE).unwrap();
+        assert_eq!(world.ambient_occlusion, None);
+
+        let scene = format!("{}- add: ambient-occlusion\n  rays: 32\n", SCENE);
+        assert!(from_str(&scene).is_err());
+    }
+
+    #[test]
+    fn loading_a_perturbed_pattern() {
+        let material = "
+pattern:
+  type: perturbed
+  scale: 0.3
+  octaves: 3
+  persistence: 0.7
+  transform:
+    - [scale, 0.5, 0.5, 0.5]
+  pattern:
+    type: stripes
+    colors:
+      - [1, 1, 1]
+      - [0, 0, 0]
+    transform:
+      - [rotate-y, 0.5]
+";
+        let yaml = YamlLoader::load_from_str(material).unwrap();
+        let material = yaml[0].as_material(&Definitions::new()).unwrap();
+        let pattern = material.pattern.unwrap();
+        assert_eq!(*pattern.transform(), Matrix4x4::scaling(0.5, 0.5, 0.5));
+        let (inner, noise) = match &pattern.design {
+            PatternDesign::Perturbed(inner, noise) => (inner, noise),
+            design => panic!("not a perturbed pattern: {:?}", design),
+        };
+        assert_eq!(*noise, Noise::new(0.3).octaves(3).persistence(0.7));
+        assert_eq!(*inner.transform(), Matrix4x4::rotation_y(0.5));
+        assert_eq!(
+            inner.design,
+            stripe_pattern(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0)).design
+        );
+
+        // the pattern to perturb is required
+        let yaml = YamlLoader::load_from_str("pattern:\n  type: perturbed\n").unwrap();
+        assert!(yaml[0].as_material(&Definitions::new()).is_err());
+    }
+
+    #[test]
+    fn loading_nested_patterns() {
+        let pattern = "
+type: stripes
+colors:
+  - [1, 0, 0]
+  - type: checkers
+    colors:
+      - [1, 1, 1]
+      - [0, 0, 0]
+    transform:
+      - [scale, 0.5, 0.5, 0.5]
+";
+        let yaml = YamlLoader::load_from_str(pattern).unwrap();
+        let pattern = yaml[0].as_pattern(&Definitions::new()).unwrap();
+        let mut checkers = checkers_pattern(white(), black());
+        checkers.set_transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
+        assert_eq!(
+            pattern,
+            stripe_pattern(Color::new(1.0, 0.0, 0.0), checkers.clone())
+        );
+
+        // combining patterns
+        let pattern = "
+type: mask
+mask:
+  type: stripes
+  colors:
+    - [1, 1, 1]
+    - [0, 0, 0]
+patterns:
+  - type: blend
+    patterns:
+      - [1, 0, 0]
+      - [0, 0, 1]
+  - type: multiply
+    patterns:
+      - [0.5, 0.5, 0.5]
+      - type: add
+        patterns:
+          - [0.2, 0.2, 0.2]
+          - [0.1, 0.1, 0.1]
+";
+        let yaml = YamlLoader::load_from_str(pattern).unwrap();
+        let pattern = yaml[0].as_pattern(&Definitions::new()).unwrap();
+        let expected = mask_pattern(
+            stripe_pattern(white(), black()),
+            blend_pattern(Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)),
+            multiply_pattern(
+                Color::new(0.5, 0.5, 0.5),
+                add_pattern(Color::new(0.2, 0.2, 0.2), Color::new(0.1, 0.1, 0.1)),
+            ),
+        );
+        assert_eq!(pattern, expected);
+
+        // a mask needs a mask
+        let yaml = YamlLoader::load_from_str("type: mask\ncolors: [[1, 1, 1], [0, 0, 0]]").unwrap();
+        assert!(yaml[0].as_pattern(&Definitions::new()).is_err());
+    }
+
+    #[test]
+    fn loading_every_pattern_type() {
+        let defs = Definitions::new();
+        let load = |pattern: &str| {
+            let yaml = YamlLoader::load_from_str(pattern).unwrap();
+            yaml[0].as_pattern(&defs)
+        };
+        let red = Color::new(1.0, 0.0, 0.0);
+        let two = "colors: [[1, 1, 1], [1, 0, 0]]";
+        for (r#type, expected) in [
+            ("stripes", stripe_pattern(white(), red)),
+            ("gradient", gradient_pattern(white(), red)),
+            ("radial-gradient", radial_gradient_pattern(white(), red)),
+            ("ring", ring_pattern(white(), red)),
+            ("smooth-ring", smooth_ring_pattern(white(), red)),
+            ("checkers", checkers_pattern(white(), red)),
+            ("blend", blend_pattern(white(), red)),
+            ("add", add_pattern(white(), red)),
+            ("multiply", multiply_pattern(white(), red)),
+        ] {
+            let pattern = load(&format!("type: {}\n{}", r#type, two)).unwrap();
+            assert_eq!(pattern, expected);
+        }
+
+        let pattern = load("type: solid\ncolor: [1, 0, 0]").unwrap();
+        assert_eq!(pattern, solid_pattern(red));
+
+        let pattern = load("type: test\ntransform: [[scale, 2, 2, 2]]").unwrap();
+        let mut expected = test_pattern();
+        expected.set_transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
+        assert_eq!(pattern, expected);
+
+        // an unknown type lists the ones that are known
+        let error = load("type: plaid\ncolors: [[1, 1, 1], [0, 0, 0]]")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("plaid"));
+        for r#type in &PATTERN_TYPES {
+            assert!(error.contains(r#type));
+        }
+    }
+
+    #[test]
+    fn loading_solid_textures() {
+        let defs = Definitions::new();
+        let load = |pattern: &str| {
+            let yaml = YamlLoader::load_from_str(pattern).unwrap();
+            yaml[0].as_pattern(&defs)
+        };
+        let red = Color::new(1.0, 0.0, 0.0);
+
+        // evenly spaced colors and the default noise
+        let pattern = load("type: marble\ncolors: [[1, 1, 1], [1, 0, 0]]").unwrap();
+        let ramp = ColorRamp::even(vec![white(), red]);
+        assert_eq!(pattern, marble_pattern(ramp, Noise::new(1.5).octaves(4)));
+
+        // colors at given stops and noise settings
+        let pattern = "
+type: wood
+colors:
+  - [1, 1, 1]
+  - [1, 0, 0]
+  - [0, 0, 0]
+stops: [0, 0.8, 1]
+scale: 0.5
+octaves: 3
+persistence: 0.25
+transform:
+  - [scale, 2, 2, 2]
+";
+        let pattern = load(pattern).unwrap();
+        let ramp = ColorRamp::new(vec![(0.0, white()), (0.8, red), (1.0, black())]);
+        let noise = Noise::new(0.5).octaves(3).persistence(0.25);
+        let mut expected = wood_pattern(ramp, noise);
+        expected.set_transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
+        assert_eq!(pattern, expected);
+
+        let pattern = load("type: granite\ncolors: [[0, 0, 0], [1, 1, 1]]").unwrap();
+        assert!(matches!(pattern.design, PatternDesign::Granite(_, _)));
+
+        // the colors are required, with a stop for each
+        assert!(load("type: wood\nscale: 0.5").is_err());
+        assert!(load("type: wood\ncolors: [[0, 0, 0], [1, 1, 1]]\nstops: [0.5]").is_err());
+    }
+
+    #[test]
+    fn loading_bumps() {
+        let defs = Definitions::new();
+        let load = |material: &str| {
+            let yaml = YamlLoader::load_from_str(material).unwrap();
+            yaml[0].as_material(&defs)
+        };
+        let material = "
+bump:
+  type: sine
+  amplitude: 0.05
+  transform:
+    - [scale, 0.5, 0.5, 0.5]
+";
+        let material = load(material).unwrap();
+        let mut bump = sine_bump(0.05);
+        bump.set_transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
+        assert_eq!(material.bump, Some(bump));
+
+        let material = "
+bump:
+  type: noise
+  scale: 0.3
+  octaves: 5
+";
+        let material = load(material).unwrap();
+        let bump = noise_bump(Noise::new(0.3).octaves(5));
+        assert_eq!(material.bump, Some(bump));
+
+        let material = "
+bump:
+  type: height-map
+  mapping: planar
+  depth: 0.2
+  uv_pattern:
+    type: checkers
+    width: 2
+    height: 2
+    colors: [[0, 0, 0], [1, 1, 1]]
+";
+        let material = load(material).unwrap();
+        let bump = height_map(
+            UvMapping::Planar,
+            uv_checkers(2.0, 2.0, black(), white()),
+            0.2,
+        );
+        assert_eq!(material.bump, Some(bump));
+
+        // height maps need a mapping and something to map
+        assert!(load("bump: {type: height-map, mapping: planar}").is_err());
+        assert!(load("bump: {type: dents}").is_err());
+
+        // noise bumps are as high as their scale, so an amplitude would go
+        // unused
+        assert!(load("bump: {type: noise, amplitude: 0.5}").is_err());
+        assert!(load("bump: {type: noise, depth: 0.5}").is_err());
+        assert_eq!(load("color: [1, 1, 1]").unwrap().bump, None);
+
+        // normal maps for triangle meshes are 2D patterns
+        let material = "
+normal-map:
+  type: checkers
+  width: 1
+  height: 1
+  colors: [[0.5, 0.5, 1], [0.5, 0.5, 1]]
+";
+        let material = load(material).unwrap();
+        let blue = Color::new(0.5, 0.5, 1.0);
+        assert_eq!(material.normal_map, Some(uv_checkers(1.0, 1.0, blue, blue)));
+    }
+
+    #[test]
+    fn loading_an_emissive_material() {
+        let yaml = YamlLoader::load_from_str("color: [1, 1, 1]\nemissive: [1, 0.2, 0.8]").unwrap();
+        let material = yaml[0].as_material(&Definitions::new()).unwrap();
+        assert_eq!(material.emissive, Color::new(1.0, 0.2, 0.8));
+    }
+
+    #[test]
+    fn group_children_inherit_the_group_material() {
+        let scene = "
+add: group
+material:
+  color: [1, 0, 0]
+children:
+  - add: sphere
+  - add: cube
+    material:
+      color: [0, 0, 1]
+";
+        let yaml = YamlLoader::load_from_str(scene).unwrap();
+        let mut group = yaml[0].as_shape(&Definitions::new()).unwrap();
+        group.refresh_parents();
+        let group = group.as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(
+            group.children[0].material().color,
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            group.children[1].material().color,
+            Color::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn loading_spot_and_directional_lights() {
+        let scene = "
+add: light
+type: spot
+at: [0, 10, 0]
+direction: [0, -1, 0]
+inner-angle: 0.2
+outer-angle: 0.4
+";
+        let yaml = YamlLoader::load_from_str(scene).unwrap();
+        let light = yaml[0].as_light().unwrap();
+        let spot = SpotLight::new(pt(0.0, 10.0, 0.0), v(0.0, -1.0, 0.0), 0.2, 0.4, white());
+        assert_eq!(light, spot.into());
+
+        // a sun with soft shadows
+        let scene = "
+add: light
+type: directional
+direction: [1, -2, 1]
+angular-diameter: 0.01
+samples: 4
+";
+        let yaml = YamlLoader::load_from_str(scene).unwrap();
+        let light = yaml[0].as_light().unwrap();
+        let sun = DirectionalLight::new(v(1.0, -2.0, 1.0), white())
+            .angular_diameter(0.01)
+            .samples(4);
+        assert_eq!(light, sun.into());
+
+        // spot lights need a direction
+        let yaml = YamlLoader::load_from_str("add: light\ntype: spot\nouter-angle: 0.4").unwrap();
+        assert!(yaml[0].as_light().is_err());
+    }
+
+    #[test]
+    fn errors_name_the_failing_item() {
+        // a camera without a field of view
+        let scene = "
+- add: camera
+  width: 100
+  height: 50
+";
+        let error = format!("{:#}", from_str(scene).err().unwrap());
+        assert!(error.contains("item 0 (add: camera)"));
+        assert!(error.contains("field-of-view"));
+
+        // an unknown shape
+        let scene = format!("{}- add: teapot\n", SCENE);
+        let error = format!("{:#}", from_str(&scene).err().unwrap());
+        assert!(error.contains("item 6 (add: teapot)"));
+        assert!(error.contains("Unknown shape: teapot"));
+
+        // a scene without a camera
+        assert!(from_str("- add: plane\n").is_err());
+    }
+}
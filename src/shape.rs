@@ -1,20 +1,195 @@
-use crate::{Intersection, Material, Matrix4x4, Ray, Tuple};
+use crate::{Bounds, Intersection, Material, Matrix4x4, Motion, Ray, Tuple};
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::{Arc, OnceLock};
+
+// the material of shapes that neither have one of their own nor belong to
+// a group that has one
+fn default_material() -> &'static Material {
+    static DEFAULT: OnceLock<Material> = OnceLock::new();
+    DEFAULT.get_or_init(Material::new)
+}
+
+// how many positions along the way a moving shape's bounds take in
+const MOTION_BOUNDS_STEPS: usize = 16;
 
 pub struct Props {
-    pub transform: Matrix4x4,
-    pub material: Material,
-    pub parent_transforms: Vec<Matrix4x4>,
+    transform: Matrix4x4,
+    // where the shape has moved to by the time the shutter closes
+    transform_end: Option<Matrix4x4>,
+    // the start and end transforms taken apart, ready to blend at any time
+    motion: Option<Motion>,
+    // cached so that intersecting and shading never has to invert a matrix
+    inverse: Matrix4x4,
+    // the shape's own material; without one it inherits the material of
+    // the group that contains it
+    material: Option<Material>,
+    inherited_material: Option<Arc<Material>>,
+    // the combined transforms of every group containing the shape, which
+    // take its parent's space to world space
+    parent_transform: Matrix4x4,
+    parent_transform_end: Matrix4x4,
+    parent_inverse: Matrix4x4,
+    parent_motion: Option<Motion>,
+    // the parent and shape transforms flattened into one world to object
+    // matrix, and its transpose for taking normals back to world space
+    world_to_object: Matrix4x4,
+    normal_to_world: Matrix4x4,
     pub shadow: bool,
 }
 
+impl Props {
+    pub fn transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4x4) {
+        self.transform = transform;
+        self.inverse = transform.inverse().unwrap();
+        self.refresh_world_to_object();
+        self.refresh_motion();
+    }
+
+    pub fn transform_end(&self) -> Option<&Matrix4x4> {
+        self.transform_end.as_ref()
+    }
+
+    pub fn set_transform_end(&mut self, transform_end: Option<Matrix4x4>) {
+        self.transform_end = transform_end;
+        self.refresh_motion();
+    }
+
+    fn refresh_motion(&mut self) {
+        self.motion = self
+            .transform_end
+            .map(|end| Motion::new(&self.transform, &end));
+    }
+
+    pub fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    pub fn inverse(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    /// The shape's own material if it has one, otherwise the material
+    /// inherited from its group, otherwise the default material.
+    pub fn material(&self) -> &Material {
+        match (&self.material, &self.inherited_material) {
+            (Some(material), _) => material,
+            (None, Some(inherited)) => inherited,
+            (None, None) => default_material(),
+        }
+    }
+
+    // gives the shape its own copy of the material it currently uses
+    pub fn material_mut(&mut self) -> &mut Material {
+        if self.material.is_none() {
+            self.material = Some(self.material().clone());
+        }
+        self.material.as_mut().unwrap()
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = Some(material);
+    }
+
+    pub fn own_material(&self) -> Option<&Material> {
+        self.material.as_ref()
+    }
+
+    pub fn set_own_material(&mut self, material: Option<Material>) {
+        self.material = material;
+    }
+
+    pub fn set_inherited_material(&mut self, material: Option<Arc<Material>>) {
+        self.inherited_material = material;
+    }
+
+    /// The inverse of the transform at `time`, which moves steadily from
+    /// `transform` to `transform_end` as time goes from 0.0 to 1.0.
+    pub fn inverse_at(&self, time: f64) -> Matrix4x4 {
+        match &self.motion {
+            Some(motion) if time != 0.0 => motion.inverse_at(time),
+            _ => self.inverse,
+        }
+    }
+
+    /// Whether the shape, or any of the groups that contain it, moves while
+    /// the shutter is open.
+    pub fn is_moving(&self) -> bool {
+        self.motion.is_some() || self.parent_motion.is_some()
+    }
+
+    fn refresh_world_to_object(&mut self) {
+        self.world_to_object = self.inverse * self.parent_inverse;
+        self.normal_to_world = self.world_to_object.transpose();
+    }
+
+    pub fn parent_transform(&self) -> &Matrix4x4 {
+        &self.parent_transform
+    }
+
+    pub fn set_parent_transform(&mut self, parent_transform: Matrix4x4) {
+        self.parent_transform = parent_transform;
+        self.parent_transform_end = parent_transform;
+        self.parent_inverse = parent_transform.inverse().unwrap();
+        self.parent_motion = None;
+        self.refresh_world_to_object();
+    }
+
+    pub fn parent_transform_end(&self) -> &Matrix4x4 {
+        &self.parent_transform_end
+    }
+
+    // must be called after set_parent_transform, which resets the parents
+    // to stand still
+    pub fn set_parent_transform_end(&mut self, parent_transform_end: Matrix4x4) {
+        self.parent_transform_end = parent_transform_end;
+        self.parent_motion = if parent_transform_end == self.parent_transform {
+            None
+        } else {
+            Some(Motion::new(&self.parent_transform, &parent_transform_end))
+        };
+    }
+
+    pub fn world_to_object(&self) -> &Matrix4x4 {
+        &self.world_to_object
+    }
+
+    pub fn normal_to_world(&self) -> &Matrix4x4 {
+        &self.normal_to_world
+    }
+
+    /// The world to object matrix for where a moving shape is at `time`.
+    pub fn world_to_object_at(&self, time: f64) -> Matrix4x4 {
+        if time == 0.0 || !self.is_moving() {
+            return self.world_to_object;
+        }
+        let parent_inverse = match &self.parent_motion {
+            Some(motion) => motion.inverse_at(time),
+            None => self.parent_inverse,
+        };
+        self.inverse_at(time) * parent_inverse
+    }
+}
+
 impl Default for Props {
     fn default() -> Props {
         Props {
             transform: Matrix4x4::identity(),
-            material: Material::new(),
-            parent_transforms: Vec::new(),
+            transform_end: None,
+            motion: None,
+            inverse: Matrix4x4::identity(),
+            material: None,
+            inherited_material: None,
+            parent_transform: Matrix4x4::identity(),
+            parent_transform_end: Matrix4x4::identity(),
+            parent_inverse: Matrix4x4::identity(),
+            parent_motion: None,
+            world_to_object: Matrix4x4::identity(),
+            normal_to_world: Matrix4x4::identity(),
             shadow: true,
         }
     }
@@ -22,7 +197,9 @@ impl Default for Props {
 
 impl PartialEq for Props {
     fn eq(&self, other: &Self) -> bool {
-        self.transform == other.transform && self.material == other.material
+        self.transform == other.transform
+            && self.transform_end == other.transform_end
+            && self.material() == other.material()
     }
 }
 
@@ -32,70 +209,105 @@ impl Debug for Props {
     }
 }
 
-pub trait Shape {
+pub trait Shape: Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
     fn shape_eq(&self, other: &dyn Any) -> bool;
     fn intersect(&'_ self, ray: &Ray) -> Vec<Intersection<'_>> {
-        let ray = ray.transform(self.transform().inverse().unwrap());
-        self.local_intersect(&ray)
+        let ray = ray.transform(self.common().inverse_at(ray.time));
+        let mut xs = self.local_intersect(&ray);
+        if ray.time != 0.0 {
+            for x in &mut xs {
+                x.time = ray.time;
+            }
+        }
+        xs
     }
     fn local_intersect(&'_ self, ray: &Ray) -> Vec<Intersection<'_>>;
     fn normal_at(&self, world_point: Tuple, i: &Intersection) -> Tuple {
-        let object_point = self.world_to_object(world_point);
+        let object_point = self.world_to_object_at(world_point, i.time);
         let object_normal = self.local_normal_at(object_point, i);
-        self.normal_to_world(object_normal)
+        self.normal_to_world_at(object_normal, i.time)
     }
     fn world_to_object(&self, point: Tuple) -> Tuple {
-        let mut point = point;
-        for transform in self.parent_transforms() {
-            point = transform.inverse().unwrap() * point;
-        }
-        self.transform().inverse().unwrap() * point
+        *self.common().world_to_object() * point
     }
     fn normal_to_world(&self, normal: Tuple) -> Tuple {
-        let shape = self;
-        fn compute_normal(transform: &Matrix4x4, normal: Tuple) -> Tuple {
-            let mut normal = transform.inverse().unwrap().transpose() * normal;
-            normal.w = 0.0;
-            normal = normal.normalize();
-            normal
-        }
-        let mut normal = compute_normal(shape.transform(), normal);
-        for transform in self.parent_transforms().iter().rev() {
-            normal = compute_normal(transform, normal);
-        }
-
-        normal
+        let mut normal = *self.common().normal_to_world() * normal;
+        normal.w = 0.0;
+        normal.normalize()
+    }
+    // the same as world_to_object, but for where a moving shape is at `time`
+    fn world_to_object_at(&self, point: Tuple, time: f64) -> Tuple {
+        self.common().world_to_object_at(time) * point
+    }
+    fn normal_to_world_at(&self, normal: Tuple, time: f64) -> Tuple {
+        let mut normal = self.common().world_to_object_at(time).transpose() * normal;
+        normal.w = 0.0;
+        normal.normalize()
     }
     fn local_normal_at(&self, local_point: Tuple, i: &Intersection) -> Tuple;
+    // the untransformed bounds of the shape in object space
+    fn bounds(&self) -> Bounds;
+    fn parent_space_bounds(&self) -> Bounds {
+        let mut bounds = self.bounds().transform(self.transform());
+        // a moving shape sweeps through everything between its two
+        // positions, which for a turning shape isn't only where it starts and
+        // ends, so take in enough of the positions along the way
+        if let Some(motion) = self.common().motion() {
+            for step in 1..=MOTION_BOUNDS_STEPS {
+                let time = step as f64 / MOTION_BOUNDS_STEPS as f64;
+                bounds.add_bounds(&self.bounds().transform(&motion.transform_at(time)));
+            }
+        }
+        bounds
+    }
+    // split large collections of children into a bounding volume hierarchy
+    fn divide(&mut self, _threshold: usize) {}
     fn common(&self) -> &Props;
     fn common_mut(&mut self) -> &mut Props;
     fn transform(&self) -> &Matrix4x4 {
-        &self.common().transform
-    }
-    fn transform_mut(&mut self) -> &mut Matrix4x4 {
-        &mut self.common_mut().transform
+        self.common().transform()
     }
     fn set_transform(&mut self, transform: Matrix4x4) {
-        self.common_mut().transform = transform;
+        self.common_mut().set_transform(transform);
+    }
+    fn set_transform_end(&mut self, transform_end: Option<Matrix4x4>) {
+        self.common_mut().set_transform_end(transform_end);
     }
     fn material(&self) -> &Material {
-        &self.common().material
+        self.common().material()
     }
     fn material_mut(&mut self) -> &mut Material {
-        &mut self.common_mut().material
+        self.common_mut().material_mut()
     }
     fn set_material(&mut self, material: Material) {
-        self.common_mut().material = material;
+        self.common_mut().set_material(material);
     }
-    fn parent_transforms(&self) -> &Vec<Matrix4x4> {
-        &self.common().parent_transforms
+    fn own_material(&self) -> Option<&Material> {
+        self.common().own_material()
     }
-    fn set_parent_transforms(&mut self, parent_transforms: Vec<Matrix4x4>) {
-        self.common_mut().parent_transforms = parent_transforms;
+    fn set_own_material(&mut self, material: Option<Material>) {
+        self.common_mut().set_own_material(material);
+    }
+    fn parent_transform(&self) -> &Matrix4x4 {
+        self.common().parent_transform()
+    }
+    fn set_parent_transform(&mut self, parent_transform: Matrix4x4) {
+        self.common_mut().set_parent_transform(parent_transform);
+    }
+    fn set_parent_transform_end(&mut self, parent_transform_end: Matrix4x4) {
+        self.common_mut()
+            .set_parent_transform_end(parent_transform_end);
     }
     fn refresh_parents(&mut self) {}
+    /// A point on the surface in object space, along with the normal there
+    /// and the area of the whole surface. `u` and `v` range from 0.0 to 1.0
+    /// and every part of the surface is equally likely to be picked. Shapes
+    /// that can't be sampled, such as infinite planes, return None.
+    fn sample_surface(&self, _u: f64, _v: f64) -> Option<(Tuple, Tuple, f64)> {
+        None
+    }
     fn includes(&self, other: &dyn Shape) -> bool;
     fn shadow(&self) -> bool {
         self.common().shadow
@@ -125,7 +337,7 @@ impl PartialEq for dyn Shape + '_ {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{pt, v};
+    use crate::{pt, v, EPSILON};
     use std::f64::consts::PI;
 
     static mut SAVED_RAY: Option<Ray> = None;
@@ -155,6 +367,10 @@ mod tests {
             v(local_point.x, local_point.y, local_point.z)
         }
 
+        fn bounds(&self) -> Bounds {
+            Bounds::new(pt(-1.0, -1.0, -1.0), pt(1.0, 1.0, 1.0))
+        }
+
         fn common(&self) -> &Props {
             &self.props
         }
@@ -200,7 +416,7 @@ mod tests {
         // assigning a material
         let mut m = Material::new();
         m.ambient = 1.0;
-        s.set_material(m);
+        s.set_material(m.clone());
         assert_eq!(s.material(), &m);
 
         // intersecting a scaled shape with a ray
@@ -224,11 +440,48 @@ mod tests {
         };
     }
 
+    #[test]
+    fn shape_bounds() {
+        // querying a shape's bounding box in its parent's space
+        let mut s = TestShape::new();
+        s.set_transform(Matrix4x4::translation(1.0, -3.0, 5.0) * Matrix4x4::scaling(0.5, 2.0, 4.0));
+        let b = s.parent_space_bounds();
+        assert_eq!(b.min, pt(0.5, -5.0, 1.0));
+        assert_eq!(b.max, pt(1.5, -1.0, 9.0));
+    }
+
+    #[test]
+    fn shape_cached_inverse() {
+        // setting a transform caches its inverse and the flattened world to
+        // object matrix
+        let mut s = TestShape::new();
+        assert_eq!(s.common().inverse(), &Matrix4x4::identity());
+        let t = Matrix4x4::translation(1.0, 2.0, 3.0) * Matrix4x4::rotation_z(PI / 3.0);
+        s.set_transform(t);
+        assert_eq!(s.common().inverse(), &t.inverse().unwrap());
+        assert_eq!(s.common().world_to_object(), &t.inverse().unwrap());
+        assert_eq!(
+            s.common().normal_to_world(),
+            &t.inverse().unwrap().transpose()
+        );
+
+        // setting the parent transform folds it into the cached matrices
+        let parent = Matrix4x4::scaling(2.0, 3.0, 4.0);
+        s.set_parent_transform(parent);
+        let world_to_object = (parent * t).inverse().unwrap();
+        assert_eq!(s.common().world_to_object(), &world_to_object);
+        assert_eq!(s.common().normal_to_world(), &world_to_object.transpose());
+
+        // and so does changing the transform afterwards
+        s.set_transform(Matrix4x4::identity());
+        assert_eq!(s.common().world_to_object(), &parent.inverse().unwrap());
+    }
+
     #[test]
     fn shape_parent() {
         // a shape has a parent attribute
         let s = TestShape::new();
-        assert!(s.parent_transforms().len() == 0);
+        assert_eq!(s.parent_transform(), &Matrix4x4::identity());
     }
 
     #[test]
@@ -248,4 +501,80 @@ mod tests {
         );
         assert_eq!(n, v(0.0, 0.97014, -0.24254));
     }
+
+    #[test]
+    fn shape_motion() {
+        // a moving shape is intersected where it is at the ray's time
+        let r = Ray::new(pt(0.0, 0.0, -5.0), v(0.0, 0.0, 1.0));
+        let mut s = TestShape::new();
+        s.set_transform(Matrix4x4::translation(0.0, 0.0, 0.0));
+        s.set_transform_end(Some(Matrix4x4::translation(4.0, 0.0, 0.0)));
+        assert!(s.common().is_moving());
+        let scenarios = vec![(0.0, -0.0), (0.5, -2.0), (1.0, -4.0)];
+        for (time, x) in scenarios {
+            unsafe {
+                SAVED_RAY = None;
+                s.intersect(&r.at_time(time));
+                assert_eq!(SAVED_RAY.unwrap().origin, pt(x, 0.0, -5.0));
+                assert_eq!(SAVED_RAY.unwrap().time, time);
+            }
+        }
+
+        // the normal is computed where the shape was at that time
+        let mut i = Intersection::new(1.0, &s);
+        i.time = 0.5;
+        let n = s.normal_at(pt(3.0, 0.0, 0.0), &i);
+        assert_eq!(n, v(1.0, 0.0, 0.0));
+        assert_eq!(
+            s.world_to_object_at(pt(3.0, 0.0, 0.0), 0.5),
+            pt(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            s.world_to_object_at(pt(3.0, 0.0, 0.0), 0.0),
+            pt(3.0, 0.0, 0.0)
+        );
+
+        // a moving shape's bounds cover everywhere it goes
+        let b = s.parent_space_bounds();
+        assert_eq!(b.min, pt(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, pt(5.0, 1.0, 1.0));
+
+        // a shape inside a moving group is moving too
+        let mut s = TestShape::new();
+        assert!(!s.common().is_moving());
+        s.set_parent_transform(Matrix4x4::identity());
+        assert!(!s.common().is_moving());
+        s.set_parent_transform_end(Matrix4x4::translation(0.0, 2.0, 0.0));
+        assert!(s.common().is_moving());
+        assert_eq!(
+            s.world_to_object_at(pt(0.0, 1.0, 0.0), 0.5),
+            pt(0.0, 0.0, 0.0)
+        );
+
+        // a shape making a half turn is a quarter of the way round halfway
+        // through, and the same shape all along
+        let mut s = TestShape::new();
+        s.set_transform_end(Some(Matrix4x4::rotation_y(PI)));
+        let inverse = s.common().inverse_at(0.5);
+        assert_eq!(inverse, Matrix4x4::rotation_y(-PI / 2.0));
+        let columns = (0..3).map(|c| v(inverse[0][c], inverse[1][c], inverse[2][c]));
+        for column in columns {
+            assert!((column.magnitude() - 1.0).abs() < EPSILON);
+        }
+        unsafe {
+            SAVED_RAY = None;
+            s.intersect(&r.at_time(0.5));
+            assert_eq!(SAVED_RAY.unwrap().origin, pt(5.0, 0.0, 0.0));
+        }
+
+        // and its bounds cover the corners it swings out along the way
+        let mut s = TestShape::new();
+        s.set_transform(Matrix4x4::scaling(2.0, 1.0, 1.0));
+        s.set_transform_end(Some(
+            Matrix4x4::rotation_y(PI / 2.0) * Matrix4x4::scaling(2.0, 1.0, 1.0),
+        ));
+        let b = s.parent_space_bounds();
+        assert!(b.max.x > 2.1);
+        assert!(b.max.z > 2.1);
+    }
 }
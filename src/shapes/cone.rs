@@ -1,4 +1,6 @@
-use crate::{equal, v, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON};
+use crate::{
+    equal, pt, v, Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON,
+};
 use std::any::Any;
 use std::f64::{INFINITY, NEG_INFINITY};
 
@@ -21,12 +23,17 @@ impl Cone {
     }
 
     pub fn transform(mut self, transform: Matrix4x4) -> Self {
-        self.props.transform = transform;
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
         self
     }
 
     pub fn material(mut self, material: Material) -> Self {
-        self.props.material = material;
+        self.props.set_material(material);
         self
     }
 
@@ -137,6 +144,11 @@ impl Shape for Cone {
         v(local_point.x, y, local_point.z)
     }
 
+    fn bounds(&self) -> Bounds {
+        let limit = self.min.abs().max(self.max.abs());
+        Bounds::new(pt(-limit, self.min, -limit), pt(limit, self.max, limit))
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -168,7 +180,6 @@ impl Shape for Cone {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pt;
 
     #[test]
     fn ray_intersection_with_cone_hits() {
@@ -255,4 +266,21 @@ mod tests {
     //     test(pt(0.5, 2.0, 0.0), v(0.0, 1.0, 0.0));
     //     test(pt(0.0, 2.0, 0.5), v(0.0, 1.0, 0.0));
     // }
+
+    #[test]
+    fn cone_bounds() {
+        // an unbounded cone has a bounding box
+        let cone = Cone::default();
+        let b = cone.bounds();
+        assert_eq!(b.min.x, NEG_INFINITY);
+        assert_eq!(b.min.y, NEG_INFINITY);
+        assert_eq!(b.max.x, INFINITY);
+        assert_eq!(b.max.y, INFINITY);
+
+        // a bounded cone has a bounding box
+        let cone = Cone::new(-5.0, 3.0, false);
+        let b = cone.bounds();
+        assert_eq!(b.min, pt(-5.0, -5.0, -5.0));
+        assert_eq!(b.max, pt(5.0, 3.0, 5.0));
+    }
 }
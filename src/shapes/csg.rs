@@ -1,4 +1,4 @@
-use crate::{Intersection, Props, Ray, Shape, Tuple};
+use crate::{Bounds, Intersection, Props, Ray, Shape, Tuple};
 use std::any::Any;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -19,15 +19,19 @@ pub struct Csg {
     op: Op,
     left: Box<dyn Shape>,
     right: Box<dyn Shape>,
+    bounds: Bounds,
 }
 
 impl Csg {
     pub fn new(op: Op, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        let mut bounds = left.parent_space_bounds();
+        bounds.add_bounds(&right.parent_space_bounds());
         Csg {
             props: Props::default(),
             op,
             left,
             right,
+            bounds,
         }
     }
 
@@ -88,6 +92,9 @@ impl Csg {
 
 impl Shape for Csg {
     fn local_intersect(&'_ self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if !self.bounds.intersects(ray) {
+            return Vec::new();
+        }
         let mut left_xs = self.left.intersect(&ray);
         let mut right_xs = self.right.intersect(&ray);
         left_xs.append(&mut right_xs);
@@ -100,6 +107,15 @@ impl Shape for Csg {
         unreachable!()
     }
 
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        self.left.divide(threshold);
+        self.right.divide(threshold);
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -225,4 +241,15 @@ mod tests {
         assert_eq!(xs[1].t, 6.5);
         assert_eq!(xs[1].object, &*c.right);
     }
+
+    #[test]
+    fn csg_bounds() {
+        // a CSG shape has a bounding box that contains its children
+        let left = Sphere::new();
+        let right = Sphere::new().transform(id().translate(2.0, 3.0, 4.0));
+        let c = Csg::difference(left.shape(), right.shape());
+        let b = c.bounds();
+        assert_eq!(b.min, pt(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, pt(3.0, 4.0, 5.0));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{v, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON};
+use crate::{pt, v, Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON};
 use std::any::Any;
 
 #[derive(PartialEq, Debug)]
@@ -14,12 +14,17 @@ impl Cube {
     }
 
     pub fn transform(mut self, transform: Matrix4x4) -> Self {
-        self.props.transform = transform;
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
         self
     }
 
     pub fn material(mut self, material: Material) -> Self {
-        self.props.material = material;
+        self.props.set_material(material);
         self
     }
 
@@ -81,6 +86,24 @@ impl Shape for Cube {
         }
     }
 
+    fn sample_surface(&self, su: f64, sv: f64) -> Option<(Tuple, Tuple, f64)> {
+        // pick one of the six faces, then a point on it
+        let face = ((su * 6.0) as usize).min(5);
+        let a = (su * 6.0 - face as f64) * 2.0 - 1.0;
+        let b = sv * 2.0 - 1.0;
+        let side = [1.0, -1.0][face % 2];
+        let (point, normal) = match face / 2 {
+            0 => (pt(side, a, b), v(side, 0.0, 0.0)),
+            1 => (pt(a, side, b), v(0.0, side, 0.0)),
+            _ => (pt(a, b, side), v(0.0, 0.0, side)),
+        };
+        Some((point, normal, 24.0))
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(pt(-1.0, -1.0, -1.0), pt(1.0, 1.0, 1.0))
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -118,7 +141,7 @@ impl<'a> From<Cube> for Box<dyn Shape + 'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pt;
+    use crate::random::Rng;
 
     #[test]
     fn ray_intersection_with_cube() {
@@ -179,4 +202,46 @@ mod tests {
         test(pt(1.0, 1.0, 1.0), v(1.0, 0.0, 0.0));
         test(pt(-1.0, -1.0, -1.0), v(-1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn cube_sample_surface() {
+        // sampled points lie on one of the faces, with that face's normal
+        let c = Cube::new();
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let (point, normal, area) = c.sample_surface(rng.next_f64(), rng.next_f64()).unwrap();
+            let maxc = point.x.abs().max(point.y.abs()).max(point.z.abs());
+            assert_eq!(maxc, 1.0);
+            assert_eq!(
+                normal,
+                c.local_normal_at(point, &Intersection::new(0.0, &c))
+            );
+            assert_eq!(area, 24.0);
+        }
+
+        // every face is sampled
+        let faces: Vec<Tuple> = (0..6)
+            .map(|face| c.sample_surface((face as f64 + 0.5) / 6.0, 0.5).unwrap().1)
+            .collect();
+        assert_eq!(
+            faces,
+            vec![
+                v(1.0, 0.0, 0.0),
+                v(-1.0, 0.0, 0.0),
+                v(0.0, 1.0, 0.0),
+                v(0.0, -1.0, 0.0),
+                v(0.0, 0.0, 1.0),
+                v(0.0, 0.0, -1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn cube_bounds() {
+        // a cube has a bounding box
+        let c = Cube::new();
+        let b = c.bounds();
+        assert_eq!(b.min, pt(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, pt(1.0, 1.0, 1.0));
+    }
 }
@@ -1,5 +1,8 @@
-use crate::{equal, v, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON};
+use crate::{
+    equal, pt, v, Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON,
+};
 use std::any::Any;
+use std::f64::consts::PI;
 use std::f64::{INFINITY, NEG_INFINITY};
 
 #[derive(PartialEq, Debug)]
@@ -21,12 +24,17 @@ impl Cylinder {
     }
 
     pub fn transform(mut self, transform: Matrix4x4) -> Self {
-        self.props.transform = transform;
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
         self
     }
 
     pub fn material(mut self, material: Material) -> Self {
-        self.props.material = material;
+        self.props.set_material(material);
         self
     }
 
@@ -127,6 +135,44 @@ impl Shape for Cylinder {
         v(local_point.x, 0.0, local_point.z)
     }
 
+    fn sample_surface(&self, su: f64, sv: f64) -> Option<(Tuple, Tuple, f64)> {
+        if self.min.is_infinite() || self.max.is_infinite() {
+            return None;
+        }
+        let side = 2.0 * PI * (self.max - self.min);
+        let area = if self.closed { side + 2.0 * PI } else { side };
+        if area <= 0.0 {
+            return None;
+        }
+
+        // pick the side or one of the caps in proportion to its area
+        let t = su * area;
+        if t < side {
+            let theta = 2.0 * PI * t / side;
+            let normal = v(theta.cos(), 0.0, theta.sin());
+            let y = self.min + sv * (self.max - self.min);
+            return Some((pt(normal.x, y, normal.z), normal, area));
+        }
+        let cap = (t - side) / PI;
+        let (y, normal) = if cap < 1.0 {
+            (self.max, v(0.0, 1.0, 0.0))
+        } else {
+            (self.min, v(0.0, -1.0, 0.0))
+        };
+        // uniformly sample the disk of the cap
+        let theta = 2.0 * PI * cap.fract();
+        let radius = sv.sqrt();
+        Some((
+            pt(radius * theta.cos(), y, radius * theta.sin()),
+            normal,
+            area,
+        ))
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(pt(-1.0, self.min, -1.0), pt(1.0, self.max, 1.0))
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -158,7 +204,7 @@ impl Shape for Cylinder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pt;
+    use crate::random::Rng;
 
     #[test]
     fn ray_intersection_with_cylinder_misses() {
@@ -267,4 +313,55 @@ mod tests {
         test(pt(0.5, 2.0, 0.0), v(0.0, 1.0, 0.0));
         test(pt(0.0, 2.0, 0.5), v(0.0, 1.0, 0.0));
     }
+
+    #[test]
+    fn cylinder_sample_surface() {
+        // an infinite cylinder can't be sampled
+        assert_eq!(Cylinder::default().sample_surface(0.5, 0.5), None);
+
+        // sampled points lie on the side or on one of the caps
+        let cyl = Cylinder::new(1.0, 2.0, true);
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let (point, normal, area) = cyl.sample_surface(rng.next_f64(), rng.next_f64()).unwrap();
+            let dist = point.x.powi(2) + point.z.powi(2);
+            if normal.y == 0.0 {
+                assert!(equal(dist, 1.0));
+                assert!(point.y >= 1.0 && point.y <= 2.0);
+            } else {
+                assert!(dist <= 1.0);
+                assert!(point.y == 1.0 || point.y == 2.0);
+            }
+            assert_eq!(
+                normal,
+                cyl.local_normal_at(point, &Intersection::new(0.0, &cyl))
+            );
+            assert!(equal(area, 4.0 * PI));
+        }
+
+        // an open cylinder is only sampled on its side
+        let (point, normal, area) = Cylinder::new(1.0, 2.0, false)
+            .sample_surface(0.99, 0.5)
+            .unwrap();
+        assert_eq!(normal.y, 0.0);
+        assert_eq!(point.y, 1.5);
+        assert!(equal(area, 2.0 * PI));
+    }
+
+    #[test]
+    fn cylinder_bounds() {
+        // an unbounded cylinder has a bounding box
+        let cyl = Cylinder::default();
+        let b = cyl.bounds();
+        assert_eq!(b.min.x, -1.0);
+        assert_eq!(b.min.y, NEG_INFINITY);
+        assert_eq!(b.max.x, 1.0);
+        assert_eq!(b.max.y, INFINITY);
+
+        // a bounded cylinder has a bounding box
+        let cyl = Cylinder::new(-5.0, 3.0, false);
+        let b = cyl.bounds();
+        assert_eq!(b.min, pt(-1.0, -5.0, -1.0));
+        assert_eq!(b.max, pt(1.0, 3.0, 1.0));
+    }
 }
@@ -1,9 +1,13 @@
-use crate::{Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple};
+use crate::{Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple};
 use std::any::Any;
+use std::sync::Arc;
+
+type Children = Vec<Box<dyn Shape>>;
 
 pub struct Group {
     props: Props,
-    pub children: Vec<Box<dyn Shape>>,
+    pub children: Children,
+    bounds: Bounds,
 }
 
 impl Group {
@@ -11,23 +15,79 @@ impl Group {
         Group {
             props: Props::default(),
             children: Vec::new(),
+            bounds: Bounds::empty(),
         }
     }
 
     pub fn transform(mut self, transform: Matrix4x4) -> Self {
-        self.props.transform = transform;
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
         self
     }
 
     pub fn material(mut self, material: Material) -> Self {
-        self.props.material = material;
+        self.props.set_material(material);
         self
     }
 
     pub fn add_child(&mut self, child: Box<dyn Shape>) {
+        self.bounds.add_bounds(&child.parent_space_bounds());
         self.children.push(child);
     }
 
+    // recompute the cached bounds in case children were changed in place
+    fn refresh_bounds(&mut self) {
+        let mut bounds = Bounds::empty();
+        for child in &self.children {
+            bounds.add_bounds(&child.parent_space_bounds());
+        }
+        self.bounds = bounds;
+    }
+
+    // split the group's bounds in two and move every child that fits entirely
+    // within one of the halves out of the group. Children that straddle both
+    // halves (or extend to infinity) stay where they are.
+    fn partition_children(&mut self) -> (Children, Children) {
+        // infinite children such as planes can never be partitioned, so
+        // leave them out when deciding where to split
+        let mut finite = Bounds::empty();
+        for child in &self.children {
+            let bounds = child.parent_space_bounds();
+            if bounds.is_finite() {
+                finite.add_bounds(&bounds);
+            }
+        }
+        let (left_bounds, right_bounds) = finite.split();
+
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut remaining = Vec::new();
+        for child in self.children.drain(..) {
+            let bounds = child.parent_space_bounds();
+            if left_bounds.contains_bounds(&bounds) {
+                left.push(child);
+            } else if right_bounds.contains_bounds(&bounds) {
+                right.push(child);
+            } else {
+                remaining.push(child);
+            }
+        }
+        self.children = remaining;
+        (left, right)
+    }
+
+    fn make_subgroup(&mut self, children: Children) {
+        let mut subgroup = Group::new();
+        for child in children {
+            subgroup.add_child(child);
+        }
+        self.children.push(subgroup.shape());
+    }
+
     pub fn shape(self) -> Box<dyn Shape> {
         Box::new(self)
     }
@@ -35,6 +95,9 @@ impl Group {
 
 impl Shape for Group {
     fn local_intersect(&'_ self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if !self.bounds.intersects(ray) {
+            return Vec::new();
+        }
         let mut xs: Vec<Intersection> = self
             .children
             .iter()
@@ -48,6 +111,33 @@ impl Shape for Group {
         unreachable!()
     }
 
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        if threshold <= self.children.len() {
+            let count = self.children.len();
+            let (left, right) = self.partition_children();
+            if left.len() == count || right.len() == count {
+                // nothing would be gained by nesting every child one level
+                // deeper, and the split would repeat forever
+                self.children = if left.is_empty() { right } else { left };
+            } else {
+                if !left.is_empty() {
+                    self.make_subgroup(left);
+                }
+                if !right.is_empty() {
+                    self.make_subgroup(right);
+                }
+            }
+        }
+        for child in &mut self.children {
+            child.divide(threshold);
+        }
+        self.refresh_bounds();
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -74,18 +164,23 @@ impl Shape for Group {
     }
 
     fn refresh_parents(&mut self) {
-        let material = self.common().material;
-        let mut child_transforms = self.parent_transforms().clone();
-        child_transforms.push(*Shape::transform(self));
+        // children without a material of their own use the group's
+        let material = Arc::new(Shape::material(self).clone());
+        let props = self.common();
+        let child_transform = *props.parent_transform() * *props.transform();
+        let transform_end = props.transform_end().unwrap_or(props.transform());
+        let child_transform_end = *props.parent_transform_end() * *transform_end;
         for child in &mut self.children {
-            if child.material() == &Material::new() {
-                child.set_material(material);
-            }
-            child.set_parent_transforms(child_transforms.clone());
+            child
+                .common_mut()
+                .set_inherited_material(Some(material.clone()));
+            child.set_parent_transform(child_transform);
+            child.set_parent_transform_end(child_transform_end);
             if let Some(group) = child.as_any_mut().downcast_mut::<Group>() {
                 group.refresh_parents();
             }
         }
+        self.refresh_bounds();
     }
 
     fn includes(&self, other: &dyn Shape) -> bool {
@@ -101,7 +196,7 @@ impl Shape for Group {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{pt, v, Sphere};
+    use crate::{id, pt, test_pattern, v, Color, Cylinder, Sphere};
     use std::f64::consts::PI;
 
     #[test]
@@ -122,8 +217,7 @@ mod tests {
         g.refresh_parents();
         assert_eq!(g.children.len(), 1);
         let s = &g.children[0];
-        assert!(s.parent_transforms().len() > 0);
-        assert_eq!(s.parent_transforms()[0], g.props.transform);
+        assert_eq!(s.parent_transform(), g.props.transform());
     }
 
     #[test]
@@ -169,6 +263,86 @@ mod tests {
         assert_eq!(xs.len(), 2);
     }
 
+    #[test]
+    fn moving_group() {
+        // the children of a moving group move with it
+        let mut g = Group::new()
+            .transform(Matrix4x4::translation(0.0, 0.0, 0.0))
+            .transform_end(Matrix4x4::translation(0.0, 4.0, 0.0));
+        g.add_child(Sphere::new().shape());
+        let r = Ray::new(pt(0.0, 2.0, -5.0), v(0.0, 0.0, 1.0));
+        assert_eq!(g.intersect(&r).len(), 0);
+        let xs = g.intersect(&r.at_time(0.5));
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[0].time, 0.5);
+
+        // and their normals are found where they were at the time of the hit
+        g.refresh_parents();
+        let xs = g.intersect(&r.at_time(0.5));
+        let n = xs[0].object.normal_at(r.position(xs[0].t), &xs[0]);
+        assert_eq!(n, v(0.0, 0.0, -1.0));
+
+        // and so are their patterns
+        let mut material = Material::new();
+        material.pattern = Some(test_pattern());
+        let mut g = Group::new()
+            .transform(Matrix4x4::translation(0.0, 0.0, 0.0))
+            .transform_end(Matrix4x4::translation(0.0, 4.0, 0.0));
+        g.add_child(Sphere::new().material(material).shape());
+        g.refresh_parents();
+        let r = Ray::new(pt(0.0, 4.0, -5.0), v(0.0, 0.0, 1.0)).at_time(1.0);
+        let xs = g.intersect(&r);
+        let object = xs[0].object;
+        let c = object
+            .material()
+            .color_at(object, r.position(xs[0].t), xs[0].time);
+        assert_eq!(c, Color::new(0.0, 0.0, -1.0));
+
+        // the group's bounds cover everywhere it goes
+        let b = g.parent_space_bounds();
+        assert_eq!(b.min, pt(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, pt(1.0, 5.0, 1.0));
+    }
+
+    #[test]
+    fn group_material_inheritance() {
+        // children without a material use their group's material
+        let red = Material::new().rgb(1.0, 0.0, 0.0);
+        let blue = Material::new().rgb(0.0, 0.0, 1.0);
+        let mut inner = Group::new();
+        inner.add_child(Sphere::new().shape());
+        let mut g = Group::new().material(red.clone());
+        g.add_child(Sphere::new().shape());
+        g.add_child(Sphere::new().material(Material::new()).shape());
+        g.add_child(Sphere::new().material(blue.clone()).shape());
+        g.add_child(inner.shape());
+        g.refresh_parents();
+        let grandchild = &g.children[3]
+            .as_any()
+            .downcast_ref::<Group>()
+            .unwrap()
+            .children[0];
+        assert_eq!(g.children[0].own_material(), None);
+        assert_eq!(g.children[0].material(), &red);
+        assert_eq!(grandchild.material(), &red);
+
+        // a child that explicitly uses the default material keeps it
+        assert_eq!(g.children[1].material(), &Material::new());
+        assert_eq!(g.children[2].material(), &blue);
+
+        // a group material set later reaches its children once refreshed
+        g.set_material(blue.clone());
+        g.refresh_parents();
+        assert_eq!(g.children[0].material(), &blue);
+        assert_eq!(g.children[1].material(), &Material::new());
+
+        // a shape outside of any group uses the default material
+        let s = Sphere::new().shape();
+        assert_eq!(s.own_material(), None);
+        assert_eq!(s.material(), &Material::new());
+    }
+
     #[test]
     fn world_to_object_space() {
         // converting a point from world to object space
@@ -183,7 +357,10 @@ mod tests {
             .downcast_ref::<Group>()
             .unwrap()
             .children[0];
-        assert!(s.parent_transforms().len() == 2);
+        assert_eq!(
+            s.parent_transform(),
+            &(Matrix4x4::rotation_y(PI / 2.0) * Matrix4x4::scaling(2.0, 2.0, 2.0))
+        );
         let p = s.world_to_object(pt(-2.0, 0.0, -10.0));
         assert_eq!(p, pt(0.0, 0.0, -1.0));
     }
@@ -210,6 +387,47 @@ mod tests {
         assert_eq!(n, v(0.28571, 0.42857, -0.85714));
     }
 
+    #[test]
+    fn nested_world_to_object() {
+        // three groups deep, the leaf's cached matrices combine every level
+        let t1 = Matrix4x4::rotation_y(PI / 2.0);
+        let t2 = Matrix4x4::scaling(1.0, 2.0, 3.0);
+        let t3 = Matrix4x4::translation(5.0, 0.0, -1.0) * Matrix4x4::rotation_x(0.3);
+        let t4 = Matrix4x4::shearing(0.5, 0.0, 0.0, 0.0, 0.0, 1.0);
+        let mut g3 = Group::new().transform(t3);
+        g3.add_child(Sphere::new().transform(t4).shape());
+        let mut g2 = Group::new().transform(t2);
+        g2.add_child(g3.shape());
+        let mut g1 = Group::new().transform(t1);
+        g1.add_child(g2.shape());
+        g1.refresh_parents();
+
+        fn leaf(g1: &Group) -> &Props {
+            let g2 = g1.children[0].as_any().downcast_ref::<Group>().unwrap();
+            let g3 = g2.children[0].as_any().downcast_ref::<Group>().unwrap();
+            g3.children[0].common()
+        }
+        let world_to_object = (t1 * t2 * t3 * t4).inverse().unwrap();
+        assert_eq!(leaf(&g1).world_to_object(), &world_to_object);
+        assert_eq!(leaf(&g1).normal_to_world(), &world_to_object.transpose());
+
+        // changing a group's transform reaches the leaf once refreshed
+        let t1 = Matrix4x4::translation(0.0, 3.0, 0.0) * Matrix4x4::rotation_z(1.0);
+        g1.set_transform(t1);
+        g1.refresh_parents();
+        let world_to_object = (t1 * t2 * t3 * t4).inverse().unwrap();
+        assert_eq!(leaf(&g1).world_to_object(), &world_to_object);
+        assert_eq!(leaf(&g1).normal_to_world(), &world_to_object.transpose());
+
+        // as does changing one in the middle
+        let t2 = Matrix4x4::scaling(0.5, 0.5, 4.0);
+        g1.children[0].set_transform(t2);
+        g1.refresh_parents();
+        let world_to_object = (t1 * t2 * t3 * t4).inverse().unwrap();
+        assert_eq!(leaf(&g1).world_to_object(), &world_to_object);
+        assert_eq!(leaf(&g1).normal_to_world(), &world_to_object.transpose());
+    }
+
     #[test]
     fn normal_on_child_object() {
         // finding the normal on a child object
@@ -231,4 +449,138 @@ mod tests {
         let n = s.normal_at(pt(1.7321, 1.1547, -5.5774), &i);
         assert_eq!(n, v(0.2857, 0.42854, -0.85716));
     }
+
+    #[test]
+    fn group_bounds() {
+        // a group has a bounding box that contains its children
+        let s = Sphere::new().transform(id().scale(2.0, 2.0, 2.0).translate(2.0, 5.0, -3.0));
+        let c = Cylinder::new(-2.0, 2.0, false)
+            .transform(id().scale(0.5, 1.0, 0.5).translate(-4.0, -1.0, 4.0));
+        let mut g = Group::new();
+        g.add_child(s.shape());
+        g.add_child(c.shape());
+        let b = g.bounds();
+        assert_eq!(b.min, pt(-4.5, -3.0, -5.0));
+        assert_eq!(b.max, pt(4.0, 7.0, 4.5));
+
+        // a ray that misses the bounding box never reaches the children
+        let r = Ray::new(pt(0.0, 0.0, -10.0), v(0.0, 1.0, 0.0));
+        assert!(!g.bounds().intersects(&r));
+        assert_eq!(g.local_intersect(&r).len(), 0);
+    }
+
+    #[test]
+    fn group_partition_children() {
+        // partitioning a group's children
+        let s1 = Sphere::new().transform(Matrix4x4::translation(-2.0, 0.0, 0.0));
+        let s2 = Sphere::new().transform(Matrix4x4::translation(2.0, 0.0, 0.0));
+        let s3 = Sphere::new();
+        let mut g = Group::new();
+        g.add_child(s1.shape());
+        g.add_child(s2.shape());
+        g.add_child(s3.shape());
+        let (left, right) = g.partition_children();
+        assert_eq!(g.children.len(), 1);
+        assert_eq!(g.children[0].transform(), &id());
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].transform(), &Matrix4x4::translation(-2.0, 0.0, 0.0));
+        assert_eq!(right.len(), 1);
+        assert_eq!(right[0].transform(), &Matrix4x4::translation(2.0, 0.0, 0.0));
+
+        // creating a sub-group from a list of children
+        let mut g = Group::new();
+        g.make_subgroup(vec![Sphere::new().shape(), Sphere::new().shape()]);
+        assert_eq!(g.children.len(), 1);
+        let subgroup = g.children[0].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(subgroup.children.len(), 2);
+    }
+
+    #[test]
+    fn group_divide() {
+        // subdividing a group partitions its children
+        let s1 = Sphere::new().transform(Matrix4x4::translation(-2.0, -2.0, 0.0));
+        let s2 = Sphere::new().transform(Matrix4x4::translation(-2.0, 2.0, 0.0));
+        let s3 = Sphere::new().transform(Matrix4x4::scaling(4.0, 4.0, 4.0));
+        let mut g = Group::new();
+        g.add_child(s1.shape());
+        g.add_child(s2.shape());
+        g.add_child(s3.shape());
+        g.divide(1);
+        assert_eq!(g.children.len(), 2);
+        assert_eq!(
+            g.children[0].transform(),
+            &Matrix4x4::scaling(4.0, 4.0, 4.0)
+        );
+        let subgroup = g.children[1].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(subgroup.children.len(), 2);
+        let first = subgroup.children[0]
+            .as_any()
+            .downcast_ref::<Group>()
+            .unwrap();
+        let second = subgroup.children[1]
+            .as_any()
+            .downcast_ref::<Group>()
+            .unwrap();
+        assert_eq!(
+            first.children[0].transform(),
+            &Matrix4x4::translation(-2.0, -2.0, 0.0)
+        );
+        assert_eq!(
+            second.children[0].transform(),
+            &Matrix4x4::translation(-2.0, 2.0, 0.0)
+        );
+
+        // subdividing a group with too few children
+        let s1 = Sphere::new().transform(Matrix4x4::translation(-2.0, 0.0, 0.0));
+        let s2 = Sphere::new().transform(Matrix4x4::translation(2.0, 1.0, 0.0));
+        let s3 = Sphere::new().transform(Matrix4x4::translation(2.0, -1.0, 0.0));
+        let mut subgroup = Group::new();
+        subgroup.add_child(s1.shape());
+        subgroup.add_child(s2.shape());
+        subgroup.add_child(s3.shape());
+        let s4 = Sphere::new();
+        let mut g = Group::new();
+        g.add_child(subgroup.shape());
+        g.add_child(s4.shape());
+        g.divide(3);
+        assert_eq!(g.children.len(), 2);
+        let subgroup = g.children[0].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(subgroup.children.len(), 2);
+        let left = subgroup.children[0]
+            .as_any()
+            .downcast_ref::<Group>()
+            .unwrap();
+        let right = subgroup.children[1]
+            .as_any()
+            .downcast_ref::<Group>()
+            .unwrap();
+        assert_eq!(left.children.len(), 1);
+        assert_eq!(
+            left.children[0].transform(),
+            &Matrix4x4::translation(-2.0, 0.0, 0.0)
+        );
+        assert_eq!(right.children.len(), 2);
+
+        // subdividing identical children does not recurse forever
+        let mut g = Group::new();
+        for _ in 0..4 {
+            g.add_child(Sphere::new().shape());
+        }
+        g.divide(1);
+        assert_eq!(g.children.len(), 4);
+
+        // dividing a group does not change what a ray hits
+        let r = Ray::new(pt(-2.0, -2.0, -5.0), v(0.0, 0.0, 1.0));
+        let mut g = Group::new();
+        for x in -3..3 {
+            for y in -3..3 {
+                let t = Matrix4x4::translation(x as f64 * 2.0, y as f64 * 2.0, 0.0);
+                g.add_child(Sphere::new().transform(t).shape());
+            }
+        }
+        let before: Vec<f64> = g.intersect(&r).iter().map(|i| i.t).collect();
+        g.divide(2);
+        let after: Vec<f64> = g.intersect(&r).iter().map(|i| i.t).collect();
+        assert_eq!(before, after);
+    }
 }
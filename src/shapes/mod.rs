@@ -5,6 +5,7 @@ mod cylinder;
 mod group;
 mod plane;
 mod sphere;
+mod torus;
 mod triangle;
 
 pub use cone::Cone;
@@ -14,4 +15,5 @@ pub use cylinder::Cylinder;
 pub use group::Group;
 pub use plane::Plane;
 pub use sphere::Sphere;
+pub use torus::Torus;
 pub use triangle::{Normal, Triangle};
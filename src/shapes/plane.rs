@@ -1,4 +1,4 @@
-use crate::{v, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON};
+use crate::{pt, v, Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple, EPSILON};
 use std::any::Any;
 
 #[derive(PartialEq, Debug)]
@@ -14,12 +14,17 @@ impl Plane {
     }
 
     pub fn transform(mut self, transform: Matrix4x4) -> Self {
-        self.props.transform = transform;
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
         self
     }
 
     pub fn material(mut self, material: Material) -> Self {
-        self.props.material = material;
+        self.props.set_material(material);
         self
     }
 
@@ -42,6 +47,13 @@ impl Shape for Plane {
         v(0.0, 1.0, 0.0)
     }
 
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            pt(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            pt(f64::INFINITY, 0.0, f64::INFINITY),
+        )
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -79,7 +91,6 @@ impl<'a> From<Plane> for Box<dyn Shape + 'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pt;
 
     #[test]
     fn ray_plane_intersection() {
@@ -121,4 +132,17 @@ mod tests {
         assert_eq!(n2, v(0.0, 1.0, 0.0));
         assert_eq!(n3, v(0.0, 1.0, 0.0));
     }
+
+    #[test]
+    fn plane_bounds() {
+        // a plane has a bounding box
+        let p = Plane::new();
+        let b = p.bounds();
+        assert_eq!(b.min.x, f64::NEG_INFINITY);
+        assert_eq!(b.min.y, 0.0);
+        assert_eq!(b.min.z, f64::NEG_INFINITY);
+        assert_eq!(b.max.x, f64::INFINITY);
+        assert_eq!(b.max.y, 0.0);
+        assert_eq!(b.max.z, f64::INFINITY);
+    }
 }
@@ -1,5 +1,6 @@
-use crate::{pt, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple};
+use crate::{pt, Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple};
 use std::any::Any;
+use std::f64::consts::PI;
 
 #[derive(PartialEq, Debug)]
 pub struct Sphere {
@@ -21,12 +22,17 @@ impl Sphere {
     }
 
     pub fn transform(mut self, transform: Matrix4x4) -> Self {
-        self.props.transform = transform;
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
         self
     }
 
     pub fn material(mut self, material: Material) -> Self {
-        self.props.material = material;
+        self.props.set_material(material);
         self
     }
 
@@ -58,6 +64,20 @@ impl Shape for Sphere {
         local_point - pt(0.0, 0.0, 0.0)
     }
 
+    fn sample_surface(&self, su: f64, sv: f64) -> Option<(Tuple, Tuple, f64)> {
+        // picking the height uniformly spreads the points evenly over the
+        // sphere (Archimedes' hat-box theorem)
+        let y = 1.0 - 2.0 * sv;
+        let radius = (1.0 - y * y).max(0.0).sqrt();
+        let theta = 2.0 * PI * su;
+        let point = pt(radius * theta.cos(), y, radius * theta.sin());
+        Some((point, point - pt(0.0, 0.0, 0.0), 4.0 * PI))
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(pt(-1.0, -1.0, -1.0), pt(1.0, 1.0, 1.0))
+    }
+
     fn common(&self) -> &Props {
         &self.props
     }
@@ -95,6 +115,7 @@ impl<'a> From<Sphere> for Box<dyn Shape + 'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::random::Rng;
     use crate::v;
 
     #[test]
@@ -198,4 +219,29 @@ mod tests {
         // the normal is a normalized vector
         assert_eq!(n, n.normalize());
     }
+
+    #[test]
+    fn sphere_sample_surface() {
+        // sampled points lie on the sphere, with the normal at that point
+        let s = Sphere::new();
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let (point, normal, area) = s.sample_surface(rng.next_f64(), rng.next_f64()).unwrap();
+            assert!(crate::equal((point - pt(0.0, 0.0, 0.0)).magnitude(), 1.0));
+            assert_eq!(
+                normal,
+                s.local_normal_at(point, &Intersection::new(0.0, &s))
+            );
+            assert_eq!(area, 4.0 * PI);
+        }
+    }
+
+    #[test]
+    fn sphere_bounds() {
+        // a sphere has a bounding box
+        let s = Sphere::new();
+        let b = s.bounds();
+        assert_eq!(b.min, pt(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, pt(1.0, 1.0, 1.0));
+    }
 }
@@ -0,0 +1,339 @@
+use crate::{pt, v, Bounds, Intersection, Material, Matrix4x4, Props, Ray, Shape, Tuple};
+use std::any::Any;
+use std::f64::consts::PI;
+
+/// A torus (donut) lying in the xz plane and centered on the origin.
+/// `major` is the distance from the origin to the center of the tube and
+/// `minor` is the radius of the tube.
+#[derive(PartialEq, Debug)]
+pub struct Torus {
+    props: Props,
+    major: f64,
+    minor: f64,
+}
+
+impl Torus {
+    pub fn new(major: f64, minor: f64) -> Torus {
+        Torus {
+            props: Props::default(),
+            major,
+            minor,
+        }
+    }
+
+    pub fn transform(mut self, transform: Matrix4x4) -> Self {
+        self.props.set_transform(transform);
+        self
+    }
+
+    pub fn transform_end(mut self, transform_end: Matrix4x4) -> Self {
+        self.props.set_transform_end(Some(transform_end));
+        self
+    }
+
+    pub fn material(mut self, material: Material) -> Self {
+        self.props.set_material(material);
+        self
+    }
+
+    pub fn shape(self) -> Box<dyn Shape> {
+        Box::new(self)
+    }
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Torus::new(1.0, 0.25)
+    }
+}
+
+// the real roots of a * t^2 + b * t + c
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return Vec::new();
+    }
+    // avoid subtracting two nearly equal numbers
+    let q = -0.5 * (b + b.signum() * disc.sqrt());
+    if q == 0.0 {
+        return vec![0.0, 0.0];
+    }
+    vec![q / a, c / q]
+}
+
+// the real roots of t^3 + a * t^2 + b * t + c
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a.powi(3) - 9.0 * a * b + 27.0 * c) / 54.0;
+    let shift = a / 3.0;
+    if r * r < q.powi(3) {
+        // three real roots
+        let theta = (r / q.powi(3).sqrt()).acos();
+        let m = -2.0 * q.sqrt();
+        vec![
+            m * (theta / 3.0).cos() - shift,
+            m * ((theta + 2.0 * PI) / 3.0).cos() - shift,
+            m * ((theta - 2.0 * PI) / 3.0).cos() - shift,
+        ]
+    } else {
+        // one real root
+        let s = -r.signum() * (r.abs() + (r * r - q.powi(3)).sqrt()).cbrt();
+        let t = if s == 0.0 { 0.0 } else { q / s };
+        vec![s + t - shift]
+    }
+}
+
+/// The real roots of c4 * t^4 + c3 * t^3 + c2 * t^2 + c1 * t + c0, found
+/// with Ferrari's method and then refined with a few steps of Newton's
+/// method, since the closed form loses precision for large coefficients.
+fn solve_quartic(c4: f64, c3: f64, c2: f64, c1: f64, c0: f64) -> Vec<f64> {
+    let (a, b, c, d) = (c3 / c4, c2 / c4, c1 / c4, c0 / c4);
+
+    // substitute t = y - a / 4 to get the depressed quartic
+    // y^4 + p * y^2 + q * y + r
+    let shift = a / 4.0;
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = c - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * c / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut ys = Vec::new();
+    if q.abs() < 1e-12 {
+        // biquadratic, so solve for y^2
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                ys.push(z.sqrt());
+                ys.push(-z.sqrt());
+            }
+        }
+    } else {
+        // find m > 0 that turns the quartic into a difference of squares
+        let m = solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0.0 {
+            return Vec::new();
+        }
+        let s = (2.0 * m).sqrt();
+        ys.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+        ys.extend(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+    }
+
+    let f = |t: f64| (((t + a) * t + b) * t + c) * t + d;
+    let df = |t: f64| ((4.0 * t + 3.0 * a) * t + 2.0 * b) * t + c;
+    let mut roots: Vec<f64> = ys
+        .into_iter()
+        .map(|y| {
+            let mut t = y - shift;
+            for _ in 0..4 {
+                let slope = df(t);
+                if slope == 0.0 {
+                    break;
+                }
+                t -= f(t) / slope;
+            }
+            t
+        })
+        .collect();
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+impl Shape for Torus {
+    fn local_intersect(&'_ self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let o = ray.origin;
+        let d = ray.direction;
+        let r2 = self.major * self.major;
+        let four_r2 = 4.0 * r2;
+
+        // points on the torus satisfy
+        // (|p|^2 - R^2 - r^2)^2 = 4R^2(r^2 - p.y^2)
+        // so substitute p = o + t * d and collect the powers of t
+        let dd = d.x * d.x + d.y * d.y + d.z * d.z;
+        let od = o.x * d.x + o.y * d.y + o.z * d.z;
+        let e = o.x * o.x + o.y * o.y + o.z * o.z - r2 - self.minor * self.minor;
+
+        let c4 = dd * dd;
+        let c3 = 4.0 * dd * od;
+        let c2 = 2.0 * dd * e + 4.0 * od * od + four_r2 * d.y * d.y;
+        let c1 = 4.0 * od * e + 2.0 * four_r2 * o.y * d.y;
+        let c0 = e * e - four_r2 * (self.minor * self.minor - o.y * o.y);
+
+        solve_quartic(c4, c3, c2, c1, c0)
+            .into_iter()
+            .map(|t| Intersection::new(t, self))
+            .collect()
+    }
+
+    fn local_normal_at(&self, local_point: Tuple, _i: &Intersection) -> Tuple {
+        // the gradient of the implicit surface
+        let p = local_point;
+        let param =
+            p.x * p.x + p.y * p.y + p.z * p.z - self.major * self.major - self.minor * self.minor;
+        v(
+            p.x * param,
+            p.y * (param + 2.0 * self.major * self.major),
+            p.z * param,
+        )
+    }
+
+    fn bounds(&self) -> Bounds {
+        let outer = self.major + self.minor;
+        Bounds::new(
+            pt(-outer, -self.minor, -outer),
+            pt(outer, self.minor, outer),
+        )
+    }
+
+    fn common(&self) -> &Props {
+        &self.props
+    }
+
+    fn common_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn shape_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self as &dyn Shape == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{equal, Csg, Group, Sphere};
+
+    fn assert_roots(roots: Vec<f64>, expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "{:?}", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!(equal(*root, *expected), "{:?} != {:?}", roots, expected);
+        }
+    }
+
+    #[test]
+    fn torus_solve_quartic() {
+        // four distinct roots: (t - 1)(t - 2)(t - 3)(t - 4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+
+        // the leading coefficient doesn't have to be 1
+        assert_roots(
+            solve_quartic(2.0, -20.0, 70.0, -100.0, 48.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+
+        // two real and two complex roots: (t^2 + 1)(t - 1)(t + 1)
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, -1.0), &[-1.0, 1.0]);
+
+        // no real roots
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+
+        // a biquadratic: (t^2 - 1)(t^2 - 4)
+        assert_roots(
+            solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0),
+            &[-2.0, -1.0, 1.0, 2.0],
+        );
+
+        // roots that are far apart: (t - 0.5)(t - 1)(t - 1000)(t - 1001)
+        assert_roots(
+            solve_quartic(1.0, -2002.5, 1004002.0, -1502500.5, 500500.0),
+            &[0.5, 1.0, 1000.0, 1001.0],
+        );
+    }
+
+    #[test]
+    fn torus_intersect() {
+        // a ray through the middle of a torus strikes it four times
+        let t = Torus::default();
+        let r = Ray::new(pt(-5.0, 0.0, 0.0), v(1.0, 0.0, 0.0));
+        let xs = t.local_intersect(&r);
+        let ts: Vec<f64> = xs.iter().map(|i| i.t).collect();
+        assert_roots(ts, &[3.75, 4.25, 5.75, 6.25]);
+
+        // a ray through the hole misses the torus
+        let r = Ray::new(pt(0.0, 5.0, 0.0), v(0.0, -1.0, 0.0));
+        assert_eq!(t.local_intersect(&r).len(), 0);
+
+        // a ray from above strikes the tube twice
+        let r = Ray::new(pt(1.0, 5.0, 0.0), v(0.0, -1.0, 0.0));
+        let ts: Vec<f64> = t.local_intersect(&r).iter().map(|i| i.t).collect();
+        assert_roots(ts, &[4.75, 5.25]);
+
+        // a ray that passes outside of the torus misses
+        let r = Ray::new(pt(-5.0, 0.5, 0.0), v(1.0, 0.0, 0.0));
+        assert_eq!(t.local_intersect(&r).len(), 0);
+
+        // a ray from far away still finds accurate intersections
+        let r = Ray::new(pt(-1000.0, 0.0, 0.0), v(1.0, 0.0, 0.0));
+        let ts: Vec<f64> = t.local_intersect(&r).iter().map(|i| i.t).collect();
+        assert_roots(ts, &[998.75, 999.25, 1000.75, 1001.25]);
+
+        // a ray with an unnormalized direction
+        let r = Ray::new(pt(-5.0, 0.0, 0.0), v(2.0, 0.0, 0.0));
+        let ts: Vec<f64> = t.local_intersect(&r).iter().map(|i| i.t).collect();
+        assert_roots(ts, &[1.875, 2.125, 2.875, 3.125]);
+    }
+
+    #[test]
+    fn torus_normal() {
+        // the normal on the surface of a torus
+        let t = Torus::default();
+        let scenarios = vec![
+            (pt(1.25, 0.0, 0.0), v(1.0, 0.0, 0.0)),
+            (pt(0.75, 0.0, 0.0), v(-1.0, 0.0, 0.0)),
+            (pt(1.0, 0.25, 0.0), v(0.0, 1.0, 0.0)),
+            (pt(1.0, -0.25, 0.0), v(0.0, -1.0, 0.0)),
+            (pt(0.0, 0.0, -1.25), v(0.0, 0.0, -1.0)),
+        ];
+        let s = Sphere::new();
+        let i = Intersection::new(1.0, &s);
+        for (point, normal) in scenarios {
+            assert_eq!(t.normal_at(point, &i), normal, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn torus_bounds() {
+        // a torus has a bounding box
+        let t = Torus::new(2.0, 0.5);
+        let b = t.bounds();
+        assert_eq!(b.min, pt(-2.5, -0.5, -2.5));
+        assert_eq!(b.max, pt(2.5, 0.5, 2.5));
+    }
+
+    #[test]
+    fn torus_in_group_and_csg() {
+        // intersecting a transformed torus inside a group
+        let mut g = Group::new().transform(Matrix4x4::translation(0.0, 0.0, 5.0));
+        g.add_child(Torus::default().shape());
+        let r = Ray::new(pt(-5.0, 0.0, 5.0), v(1.0, 0.0, 0.0));
+        assert_eq!(g.intersect(&r).len(), 4);
+
+        // a torus with a sphere cut out of it
+        let c = Csg::difference(
+            Torus::default().shape(),
+            Sphere::new()
+                .transform(Matrix4x4::translation(1.0, 0.0, 0.0))
+                .shape(),
+        );
+        let r = Ray::new(pt(-5.0, 0.0, 0.0), v(1.0, 0.0, 0.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!(equal(xs[0].t, 3.75));
+        assert!(equal(xs[1].t, 4.25));
+    }
+}
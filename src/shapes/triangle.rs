@@ -1,4 +1,4 @@
-use crate::{Intersection, Props, Ray, Shape, Tuple, EPSILON};
+use crate::{Bounds, Intersection, Props, Ray, Shape, Tuple, EPSILON};
 use std::any::Any;
 
 #[derive(PartialEq, Debug)]
@@ -16,6 +16,11 @@ pub struct Triangle {
     pub e1: Tuple,
     pub e2: Tuple,
     pub normal: Normal,
+    // texture coordinates at each corner, used to look up a normal map
+    pub uvs: Option<[(f64, f64); 3]>,
+    // the directions in which the texture's u and v increase across the
+    // triangle, the tangent and bitangent
+    pub tangents: Option<(Tuple, Tuple)>,
 }
 
 impl Triangle {
@@ -31,6 +36,8 @@ impl Triangle {
             e1,
             e2,
             normal: Normal::Default(normal),
+            uvs: None,
+            tangents: None,
         }
     }
 
@@ -45,9 +52,64 @@ impl Triangle {
             normal: Normal::Smooth(n1, n2, n3),
             e1,
             e2,
+            uvs: None,
+            tangents: None,
         }
     }
 
+    /// Gives each corner texture coordinates, from which the tangent and
+    /// bitangent are found.
+    pub fn uvs(mut self, uv1: (f64, f64), uv2: (f64, f64), uv3: (f64, f64)) -> Triangle {
+        let (du1, dv1) = (uv2.0 - uv1.0, uv2.1 - uv1.1);
+        let (du2, dv2) = (uv3.0 - uv1.0, uv3.1 - uv1.1);
+        let determinant = du1 * dv2 - du2 * dv1;
+        self.tangents = if determinant.abs() < EPSILON {
+            // the texture is squashed flat across the triangle
+            None
+        } else {
+            let f = 1.0 / determinant;
+            let tangent = (self.e1 * dv2 - self.e2 * dv1) * f;
+            let bitangent = (self.e2 * du1 - self.e1 * du2) * f;
+            Some((tangent, bitangent))
+        };
+        self.uvs = Some([uv1, uv2, uv3]);
+        self
+    }
+
+    // tilts the normal by the material's normal map, whose red, green and
+    // blue run from -1.0 to 1.0 along the tangent, bitangent and normal
+    fn normal_mapped(&self, normal: Tuple, i: &Intersection) -> Tuple {
+        let map = match &self.material().normal_map {
+            Some(map) => map,
+            None => return normal,
+        };
+        let (uvs, (tangent, bitangent), (u, v)) = match (self.uvs, self.tangents, i.uv) {
+            (Some(uvs), Some(tangents), Some(uv)) => (uvs, tangents, uv),
+            _ => return normal,
+        };
+        let w = 1.0 - u - v;
+        let tu = uvs[0].0 * w + uvs[1].0 * u + uvs[2].0 * v;
+        let tv = uvs[0].1 * w + uvs[1].1 * u + uvs[2].1 * v;
+        let color = map.uv_pattern_at(tu, tv);
+
+        // square the tangent up with the (possibly smoothed) normal, and keep
+        // the bitangent on the same side as the texture's v
+        let normal = normal.normalize();
+        let tangent = tangent - normal * normal.dot(&tangent);
+        if tangent.magnitude() < EPSILON {
+            return normal;
+        }
+        let tangent = tangent.normalize();
+        let mut side = normal * tangent;
+        if side.dot(&bitangent) < 0.0 {
+            side = -side;
+        }
+        (tangent * (color.red * 2.0 - 1.0)
+            + side * (color.green * 2.0 - 1.0)
+            + normal * (color.blue * 2.0 - 1.0))
+            .normalize()
+    }
+
     pub fn shape(self) -> Box<dyn Shape> {
         Box::new(self)
     }
@@ -80,7 +142,7 @@ impl Shape for Triangle {
     }
 
     fn local_normal_at(&self, _local_point: Tuple, i: &Intersection) -> Tuple {
-        match self.normal {
+        let normal = match self.normal {
             Normal::Default(normal) => normal,
             Normal::Smooth(n1, n2, n3) => {
                 if let Some((u, v)) = i.uv {
@@ -89,7 +151,33 @@ impl Shape for Triangle {
                     panic!("uv not set on intersection!")
                 }
             }
+        };
+        self.normal_mapped(normal, i)
+    }
+
+    fn sample_surface(&self, su: f64, sv: f64) -> Option<(Tuple, Tuple, f64)> {
+        let normal = self.e2 * self.e1;
+        let area = normal.magnitude() / 2.0;
+        if area == 0.0 {
+            return None;
         }
+        // fold points from the far half of the parallelogram back into the
+        // triangle
+        let (su, sv) = if su + sv > 1.0 {
+            (1.0 - su, 1.0 - sv)
+        } else {
+            (su, sv)
+        };
+        let point = self.p1 + self.e1 * su + self.e2 * sv;
+        Some((point, normal.normalize(), area))
+    }
+
+    fn bounds(&self) -> Bounds {
+        let mut bounds = Bounds::empty();
+        bounds.add_point(self.p1);
+        bounds.add_point(self.p2);
+        bounds.add_point(self.p3);
+        bounds
     }
 
     fn common(&self) -> &Props {
@@ -129,7 +217,8 @@ impl<'a> From<Triangle> for Box<dyn Shape + 'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{equal, pt, v};
+    use crate::random::Rng;
+    use crate::{equal, pt, uv_image, v, Canvas, Color, Material};
 
     #[test]
     fn triangle_create() {
@@ -242,4 +331,107 @@ mod tests {
         let comps = i.prepare_computations(&r, &xs);
         assert_eq!(comps.normalv, v(-0.5547, 0.83205, 0.0));
     }
+
+    #[test]
+    fn triangle_sample_surface() {
+        let t = Triangle::new(pt(0.0, 1.0, 0.0), pt(-1.0, 0.0, 0.0), pt(1.0, 0.0, 0.0));
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let (point, normal, area) = t.sample_surface(rng.next_f64(), rng.next_f64()).unwrap();
+            // the point is inside the triangle
+            assert_eq!(point.z, 0.0);
+            assert!(point.y >= 0.0 && point.y <= 1.0 - point.x.abs());
+            assert_eq!(normal, v(0.0, 0.0, -1.0));
+            assert_eq!(area, 1.0);
+        }
+    }
+
+    #[test]
+    fn triangle_tangents() {
+        // a triangle without texture coordinates has no tangents
+        let t = Triangle::new(pt(0.0, 1.0, 0.0), pt(-1.0, 0.0, 0.0), pt(1.0, 0.0, 0.0));
+        assert_eq!(t.uvs, None);
+        assert_eq!(t.tangents, None);
+
+        // the tangent and bitangent follow u and v across the triangle
+        let t = t.uvs((0.5, 1.0), (0.0, 0.0), (1.0, 0.0));
+        assert_eq!(t.uvs, Some([(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]));
+        let (tangent, bitangent) = t.tangents.unwrap();
+        assert_eq!(tangent, v(2.0, 0.0, 0.0));
+        assert_eq!(bitangent, v(0.0, 1.0, 0.0));
+
+        // and are scaled by how much of the texture the triangle covers
+        let t = Triangle::new(pt(0.0, 0.0, 0.0), pt(2.0, 0.0, 0.0), pt(0.0, 0.0, 4.0));
+        let (tangent, bitangent) = t.uvs((0.0, 0.0), (0.0, 1.0), (1.0, 0.0)).tangents.unwrap();
+        assert_eq!(tangent, v(0.0, 0.0, 4.0));
+        assert_eq!(bitangent, v(2.0, 0.0, 0.0));
+
+        // texture coordinates all in a line give no tangents
+        let t = Triangle::new(pt(0.0, 1.0, 0.0), pt(-1.0, 0.0, 0.0), pt(1.0, 0.0, 0.0));
+        let t = t.uvs((0.0, 0.0), (0.5, 0.5), (1.0, 1.0));
+        assert_eq!(t.tangents, None);
+    }
+
+    #[test]
+    fn triangle_normal_map() {
+        let normal_map = |color: Color| {
+            let mut canvas = Canvas::new(1, 1);
+            canvas.write_pixel(0, 0, color);
+            uv_image(canvas)
+        };
+        let mut t = Triangle::new(pt(0.0, 1.0, 0.0), pt(-1.0, 0.0, 0.0), pt(1.0, 0.0, 0.0)).uvs(
+            (0.5, 1.0),
+            (0.0, 0.0),
+            (1.0, 0.0),
+        );
+        let i = Intersection::with_uv(1.0, &t, 0.25, 0.25);
+        assert_eq!(t.local_normal_at(pt(0.0, 0.5, 0.0), &i), v(0.0, 0.0, -1.0));
+
+        // a map pointing straight out leaves the normal alone
+        t.set_material(Material::new().normal_map(normal_map(Color::new(0.5, 0.5, 1.0))));
+        let i = Intersection::with_uv(1.0, &t, 0.25, 0.25);
+        assert_eq!(t.local_normal_at(pt(0.0, 0.5, 0.0), &i), v(0.0, 0.0, -1.0));
+
+        // red tilts it towards the tangent, and green towards the bitangent
+        t.set_material(Material::new().normal_map(normal_map(Color::new(1.0, 0.5, 0.5))));
+        let i = Intersection::with_uv(1.0, &t, 0.25, 0.25);
+        assert_eq!(t.local_normal_at(pt(0.0, 0.5, 0.0), &i), v(1.0, 0.0, 0.0));
+        t.set_material(Material::new().normal_map(normal_map(Color::new(0.5, 1.0, 0.5))));
+        let i = Intersection::with_uv(1.0, &t, 0.25, 0.25);
+        assert_eq!(t.local_normal_at(pt(0.0, 0.5, 0.0), &i), v(0.0, 1.0, 0.0));
+
+        // the map tilts smoothed normals too
+        let n = v(0.0, 0.0, -1.0);
+        let mut t = Triangle::smooth(
+            pt(0.0, 1.0, 0.0),
+            pt(-1.0, 0.0, 0.0),
+            pt(1.0, 0.0, 0.0),
+            n,
+            v(-1.0, 0.0, 0.0),
+            v(1.0, 0.0, 0.0),
+        )
+        .uvs((0.5, 1.0), (0.0, 0.0), (1.0, 0.0));
+        t.set_material(Material::new().normal_map(normal_map(Color::new(0.5, 1.0, 0.5))));
+        let i = Intersection::with_uv(1.0, &t, 0.0, 0.0);
+        assert_eq!(t.local_normal_at(pt(0.0, 1.0, 0.0), &i), v(0.0, 1.0, 0.0));
+        t.set_material(Material::new().normal_map(normal_map(Color::new(1.0, 0.5, 0.5))));
+        let i = Intersection::with_uv(1.0, &t, 0.0, 0.5);
+        let tilted = v(1.0, 0.0, 1.0).normalize();
+        assert_eq!(t.local_normal_at(pt(0.5, 0.5, 0.0), &i), tilted);
+
+        // without texture coordinates the map is ignored
+        let mut t = Triangle::new(pt(0.0, 1.0, 0.0), pt(-1.0, 0.0, 0.0), pt(1.0, 0.0, 0.0));
+        t.set_material(Material::new().normal_map(normal_map(Color::new(1.0, 0.5, 0.5))));
+        let i = Intersection::with_uv(1.0, &t, 0.25, 0.25);
+        assert_eq!(t.local_normal_at(pt(0.0, 0.5, 0.0), &i), v(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn triangle_bounds() {
+        // a triangle has a bounding box
+        let t = Triangle::new(pt(-3.0, 7.0, 2.0), pt(6.0, 2.0, -4.0), pt(2.0, -1.0, -1.0));
+        let b = t.bounds();
+        assert_eq!(b.min, pt(-3.0, -1.0, -4.0));
+        assert_eq!(b.max, pt(6.0, 7.0, 2.0));
+    }
 }
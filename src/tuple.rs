@@ -1,6 +1,9 @@
 use crate::EPSILON;
+use anyhow::{anyhow, Result};
 use image::{ImageBuffer, RgbImage};
+use std::ffi::OsStr;
 use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::path::Path;
 use std::{
     cmp::{max, min},
     iter::Sum,
@@ -290,6 +293,18 @@ impl Mul<f64> for Color {
     }
 }
 
+impl Div<f64> for Color {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self {
+            red: self.red / rhs,
+            green: self.green / rhs,
+            blue: self.blue / rhs,
+        }
+    }
+}
+
 /// Hadamard product (or Schur product)
 /// Multiply corresponding components of each color to form a new color.
 impl Mul for Color {
@@ -306,6 +321,7 @@ impl Mul for Color {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     width: usize,
     height: usize,
@@ -322,6 +338,14 @@ impl Canvas {
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     fn xy(&self, x: usize, y: usize) -> usize {
         x + y * self.width
     }
@@ -388,6 +412,69 @@ impl Canvas {
         }
         img
     }
+
+    pub fn from_image(img: &RgbImage) -> Canvas {
+        let mut canvas = Canvas::new(img.width() as usize, img.height() as usize);
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let [red, green, blue] = pixel.0;
+            canvas.write_pixel(
+                x as usize,
+                y as usize,
+                Color::new(
+                    red as f64 / 255.0,
+                    green as f64 / 255.0,
+                    blue as f64 / 255.0,
+                ),
+            );
+        }
+        canvas
+    }
+
+    pub fn from_ppm(contents: &str) -> Result<Canvas> {
+        // comments run from a # to the end of the line
+        let mut tokens = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(|line| line.split_whitespace());
+        let mut next = |what: &str| {
+            tokens
+                .next()
+                .ok_or_else(|| anyhow!("PPM ended before reading the {}", what))
+        };
+        let magic = next("magic number")?;
+        if magic != "P3" {
+            return Err(anyhow!("Unsupported PPM magic number: {}", magic));
+        }
+        let width: usize = next("width")?.parse()?;
+        let height: usize = next("height")?.parse()?;
+        if width == 0 || height == 0 {
+            return Err(anyhow!("PPM has no pixels: {}x{}", width, height));
+        }
+        let scale: f64 = next("maximum color value")?.parse()?;
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let red: f64 = next("pixel data")?.parse()?;
+                let green: f64 = next("pixel data")?.parse()?;
+                let blue: f64 = next("pixel data")?.parse()?;
+                canvas.write_pixel(x, y, Color::new(red / scale, green / scale, blue / scale));
+            }
+        }
+        Ok(canvas)
+    }
+
+    /// Read a PPM or any other image format supported by the image crate
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Canvas> {
+        let path = path.as_ref();
+        if path.extension() == Some(OsStr::new("ppm")) {
+            return Canvas::from_ppm(&std::fs::read_to_string(path)?);
+        }
+        let canvas = Canvas::from_image(&image::open(path)?.to_rgb8());
+        if canvas.width() == 0 || canvas.height() == 0 {
+            return Err(anyhow!("{} has no pixels", path.display()));
+        }
+        Ok(canvas)
+    }
 }
 
 #[cfg(test)]
@@ -558,6 +645,10 @@ mod tests {
         let c1 = Color::new(1.0, 0.2, 0.4);
         let c2 = Color::new(0.9, 1.0, 0.1);
         assert_eq!(c1 * c2, Color::new(0.9, 0.2, 0.04));
+
+        // dividing a color by a scalar
+        let c = Color::new(0.2, 0.3, 0.4);
+        assert_eq!(c / 2.0, Color::new(0.1, 0.15, 0.2));
     }
 
     #[test]
@@ -619,6 +710,84 @@ mod tests {
         assert_eq!(ppm.as_bytes()[ppm.len() - 1], '\n' as u8);
     }
 
+    #[test]
+    fn canvas_from_ppm() {
+        // reading a file with the wrong magic number
+        let ppm = "P32\n1 1\n255\n0 0 0\n";
+        assert!(Canvas::from_ppm(ppm).is_err());
+
+        // reading a PPM returns a canvas of the right size
+        let ppm = "P3\n10 2\n255\n".to_string() + &"0 0 0\n".repeat(20);
+        let c = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(c.width(), 10);
+        assert_eq!(c.height(), 2);
+
+        // reading pixel data from a PPM file
+        let ppm = "P3
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255
+255 255 0  0 255 255  255 0 255  127 127 127
+";
+        let c = Canvas::from_ppm(ppm).unwrap();
+        let scenarios = vec![
+            (0, 0, Color::new(1.0, 0.49804, 0.0)),
+            (1, 0, Color::new(0.0, 0.49804, 1.0)),
+            (2, 0, Color::new(0.49804, 1.0, 0.0)),
+            (3, 0, Color::new(1.0, 1.0, 1.0)),
+            (0, 1, Color::new(0.0, 0.0, 0.0)),
+            (1, 1, Color::new(1.0, 0.0, 0.0)),
+            (2, 1, Color::new(0.0, 1.0, 0.0)),
+            (3, 1, Color::new(0.0, 0.0, 1.0)),
+            (0, 2, Color::new(1.0, 1.0, 0.0)),
+            (1, 2, Color::new(0.0, 1.0, 1.0)),
+            (2, 2, Color::new(1.0, 0.0, 1.0)),
+            (3, 2, Color::new(0.49804, 0.49804, 0.49804)),
+        ];
+        for (x, y, color) in scenarios {
+            assert_eq!(c.pixel_at(x, y), color);
+        }
+
+        // PPM parsing ignores comment lines
+        let ppm = "P3
+# this is a comment
+2 1
+# this, too
+255
+# another comment
+255 255 255
+# oh, no, comments in the pixel data!
+255 0 255
+";
+        let c = Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(c.pixel_at(1, 0), Color::new(1.0, 0.0, 1.0));
+
+        // PPM parsing allows an RGB triple to span lines
+        let ppm = "P3\n1 1\n255\n51\n153\n\n204\n";
+        let c = Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(0.2, 0.6, 0.8));
+
+        // PPM parsing respects the scale setting
+        let ppm = "P3\n2 2\n100\n100 100 100  50 50 50\n75 50 25  0 0 0\n";
+        let c = Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(c.pixel_at(0, 1), Color::new(0.75, 0.5, 0.25));
+
+        // a canvas survives a round trip through a PPM
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(1, 1, Color::new(1.0, 0.0, 1.0));
+        assert_eq!(Canvas::from_ppm(&c.to_ppm()).unwrap(), c);
+
+        // reading truncated pixel data fails
+        let ppm = "P3\n2 1\n255\n255 255 255\n";
+        assert!(Canvas::from_ppm(ppm).is_err());
+
+        // an image needs at least one pixel
+        assert!(Canvas::from_ppm("P3\n0 0\n255\n").is_err());
+        assert!(Canvas::from_ppm("P3\n3 0\n255\n").is_err());
+    }
+
     #[test]
     fn vector_reflection() {
         // reflecting a vector approaching at 45 degrees
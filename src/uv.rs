@@ -0,0 +1,527 @@
+use crate::{Canvas, Color, Tuple};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A two dimensional pattern, evaluated at a (u, v) coordinate where both
+/// u and v are between 0.0 and 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UvPattern {
+    Checkers {
+        width: f64,
+        height: f64,
+        a: Color,
+        b: Color,
+    },
+    AlignCheck {
+        main: Color,
+        ul: Color,
+        ur: Color,
+        bl: Color,
+        br: Color,
+    },
+    Image(UvImage),
+}
+
+impl UvPattern {
+    pub fn uv_pattern_at(&self, u: f64, v: f64) -> Color {
+        match self {
+            UvPattern::Checkers {
+                width,
+                height,
+                a,
+                b,
+            } => {
+                let u2 = (u * width).floor();
+                let v2 = (v * height).floor();
+                if (u2 + v2) as isize % 2 == 0 {
+                    *a
+                } else {
+                    *b
+                }
+            }
+            UvPattern::AlignCheck {
+                main,
+                ul,
+                ur,
+                bl,
+                br,
+            } => {
+                if v > 0.8 {
+                    if u < 0.2 {
+                        return *ul;
+                    }
+                    if u > 0.8 {
+                        return *ur;
+                    }
+                } else if v < 0.2 {
+                    if u < 0.2 {
+                        return *bl;
+                    }
+                    if u > 0.8 {
+                        return *br;
+                    }
+                }
+                *main
+            }
+            UvPattern::Image(image) => image.uv_pattern_at(u, v),
+        }
+    }
+}
+
+pub fn uv_checkers(width: f64, height: f64, a: Color, b: Color) -> UvPattern {
+    UvPattern::Checkers {
+        width,
+        height,
+        a,
+        b,
+    }
+}
+
+pub fn uv_align_check(main: Color, ul: Color, ur: Color, bl: Color, br: Color) -> UvPattern {
+    UvPattern::AlignCheck {
+        main,
+        ul,
+        ur,
+        bl,
+        br,
+    }
+}
+
+pub fn uv_image(canvas: Canvas) -> UvPattern {
+    UvPattern::Image(UvImage::new(canvas))
+}
+
+/// How an image is sampled between the centers of its pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+}
+
+/// What happens to (u, v) coordinates that fall outside of the image.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureWrap {
+    Repeat,
+    Clamp,
+}
+
+/// An image draped over a shape. The canvas is shared so that copies of
+/// the pattern don't duplicate the pixel data.
+#[derive(Debug, Clone)]
+pub struct UvImage {
+    canvas: Arc<Canvas>,
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+}
+
+// shapes are compared while shading, so compare images by identity rather
+// than pixel by pixel
+impl PartialEq for UvImage {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.canvas, &other.canvas)
+            && self.filter == other.filter
+            && self.wrap == other.wrap
+    }
+}
+
+impl UvImage {
+    pub fn new(canvas: Canvas) -> UvImage {
+        UvImage {
+            canvas: Arc::new(canvas),
+            filter: TextureFilter::Nearest,
+            wrap: TextureWrap::Repeat,
+        }
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    pub fn filter(mut self, filter: TextureFilter) -> UvImage {
+        self.filter = filter;
+        self
+    }
+
+    pub fn wrap(mut self, wrap: TextureWrap) -> UvImage {
+        self.wrap = wrap;
+        self
+    }
+
+    // bring a coordinate that falls outside of 0..=1 back into range
+    fn wrap_uv(&self, n: f64) -> f64 {
+        if (0.0..=1.0).contains(&n) {
+            return n;
+        }
+        match self.wrap {
+            TextureWrap::Repeat => n.rem_euclid(1.0),
+            TextureWrap::Clamp => n.clamp(0.0, 1.0),
+        }
+    }
+
+    // the pixel index for a column or row that may be off the edge
+    fn wrap_index(&self, i: isize, size: usize) -> usize {
+        match self.wrap {
+            TextureWrap::Repeat => i.rem_euclid(size as isize) as usize,
+            TextureWrap::Clamp => i.clamp(0, size as isize - 1) as usize,
+        }
+    }
+
+    pub fn uv_pattern_at(&self, u: f64, v: f64) -> Color {
+        let (width, height) = (self.canvas.width(), self.canvas.height());
+        let u = self.wrap_uv(u);
+        // flip v over so it matches the image layout, where
+        // y increases from the top down
+        let v = 1.0 - self.wrap_uv(v);
+        match self.filter {
+            TextureFilter::Nearest => {
+                let x = u * (width - 1) as f64;
+                let y = v * (height - 1) as f64;
+                self.canvas.pixel_at(x.round() as usize, y.round() as usize)
+            }
+            TextureFilter::Bilinear => {
+                // blend the four pixels whose centers surround the point
+                let x = u * width as f64 - 0.5;
+                let y = v * height as f64 - 0.5;
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let pixel = |x: isize, y: isize| {
+                    self.canvas
+                        .pixel_at(self.wrap_index(x, width), self.wrap_index(y, height))
+                };
+                let top = pixel(x0, y0) * (1.0 - fx) + pixel(x0 + 1, y0) * fx;
+                let bottom = pixel(x0, y0 + 1) * (1.0 - fx) + pixel(x0 + 1, y0 + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+/// How a point on the surface of a shape is converted to a (u, v) coordinate.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+    Cube,
+}
+
+impl UvMapping {
+    pub fn map(&self, point: Tuple) -> (f64, f64) {
+        match self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cube => cubic_map(point),
+        }
+    }
+}
+
+pub fn spherical_map(point: Tuple) -> (f64, f64) {
+    // compute the azimuthal angle
+    // -π < theta <= π
+    // angle increases clockwise as viewed from above,
+    // which is opposite of what we want, but we'll fix it later.
+    let theta = point.x.atan2(point.z);
+
+    // vec is the vector pointing from the sphere's origin (the world origin)
+    // to the point, which will also happen to be exactly equal to the sphere's
+    // radius.
+    let radius = Tuple::vector(point.x, point.y, point.z).magnitude();
+
+    // compute the polar angle
+    // 0 <= phi <= π
+    let phi = (point.y / radius).acos();
+
+    // -0.5 < raw_u <= 0.5
+    let raw_u = theta / (2.0 * PI);
+
+    // 0 <= u < 1
+    // here's also where we fix the direction of u. Subtract it from 1,
+    // so that it increases counterclockwise as viewed from above.
+    let u = 1.0 - (raw_u + 0.5);
+
+    // we want v to be 0 at the south pole of the sphere,
+    // and 1 at the north pole, so we have to "flip it over"
+    // by subtracting it from 1.
+    let v = 1.0 - phi / PI;
+
+    (u, v)
+}
+
+pub fn planar_map(point: Tuple) -> (f64, f64) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+pub fn cylindrical_map(point: Tuple) -> (f64, f64) {
+    // compute the azimuthal angle, same as with spherical_map()
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+
+    // let v go from 0 to 1 between whole units of y
+    let v = point.y.rem_euclid(1.0);
+
+    (u, v)
+}
+
+/// The faces of a cube, in the order their patterns are stored in a cube map.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    pub fn from_point(point: Tuple) -> CubeFace {
+        let coord = point.x.abs().max(point.y.abs()).max(point.z.abs());
+        if coord == point.x {
+            CubeFace::Right
+        } else if coord == -point.x {
+            CubeFace::Left
+        } else if coord == point.y {
+            CubeFace::Up
+        } else if coord == -point.y {
+            CubeFace::Down
+        } else if coord == point.z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    pub fn uv(&self, point: Tuple) -> (f64, f64) {
+        let (u, v) = match self {
+            CubeFace::Front => (point.x + 1.0, point.y + 1.0),
+            CubeFace::Back => (1.0 - point.x, point.y + 1.0),
+            CubeFace::Left => (point.z + 1.0, point.y + 1.0),
+            CubeFace::Right => (1.0 - point.z, point.y + 1.0),
+            CubeFace::Up => (point.x + 1.0, 1.0 - point.z),
+            CubeFace::Down => (point.x + 1.0, point.z + 1.0),
+        };
+        (u.rem_euclid(2.0) / 2.0, v.rem_euclid(2.0) / 2.0)
+    }
+}
+
+pub fn cubic_map(point: Tuple) -> (f64, f64) {
+    CubeFace::from_point(point).uv(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{black, equal, pt, white};
+
+    #[test]
+    fn uv_checkers_pattern() {
+        // checker pattern in 2D
+        let checkers = uv_checkers(2.0, 2.0, black(), white());
+        let scenarios = vec![
+            (0.0, 0.0, black()),
+            (0.5, 0.0, white()),
+            (0.0, 0.5, white()),
+            (0.5, 0.5, black()),
+            (1.0, 1.0, black()),
+        ];
+        for (u, v, expected) in scenarios {
+            assert_eq!(checkers.uv_pattern_at(u, v), expected);
+        }
+    }
+
+    #[test]
+    fn uv_align_check_pattern() {
+        // layout of the "align check" pattern
+        let main = Color::new(1.0, 1.0, 1.0);
+        let ul = Color::new(1.0, 0.0, 0.0);
+        let ur = Color::new(1.0, 1.0, 0.0);
+        let bl = Color::new(0.0, 1.0, 0.0);
+        let br = Color::new(0.0, 1.0, 1.0);
+        let pattern = uv_align_check(main, ul, ur, bl, br);
+        let scenarios = vec![
+            (0.5, 0.5, main),
+            (0.1, 0.9, ul),
+            (0.9, 0.9, ur),
+            (0.1, 0.1, bl),
+            (0.9, 0.1, br),
+        ];
+        for (u, v, expected) in scenarios {
+            assert_eq!(pattern.uv_pattern_at(u, v), expected);
+        }
+    }
+
+    #[test]
+    fn uv_spherical_map() {
+        // using a spherical mapping on a 3D point
+        let scenarios = vec![
+            (pt(0.0, 0.0, -1.0), 0.0, 0.5),
+            (pt(1.0, 0.0, 0.0), 0.25, 0.5),
+            (pt(0.0, 0.0, 1.0), 0.5, 0.5),
+            (pt(-1.0, 0.0, 0.0), 0.75, 0.5),
+            (pt(0.0, 1.0, 0.0), 0.5, 1.0),
+            (pt(0.0, -1.0, 0.0), 0.5, 0.0),
+            (
+                pt(2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0, 0.0),
+                0.25,
+                0.75,
+            ),
+        ];
+        for (point, u, v) in scenarios {
+            let (mu, mv) = spherical_map(point);
+            assert!(equal(mu, u) && equal(mv, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn uv_planar_map() {
+        // using a planar mapping on a 3D point
+        let scenarios = vec![
+            (pt(0.25, 0.0, 0.5), 0.25, 0.5),
+            (pt(0.25, 0.0, -0.25), 0.25, 0.75),
+            (pt(0.25, 0.5, -0.25), 0.25, 0.75),
+            (pt(1.25, 0.0, 0.5), 0.25, 0.5),
+            (pt(0.25, 0.0, -1.75), 0.25, 0.25),
+            (pt(1.0, 0.0, -1.0), 0.0, 0.0),
+            (pt(0.0, 0.0, 0.0), 0.0, 0.0),
+        ];
+        for (point, u, v) in scenarios {
+            assert_eq!(planar_map(point), (u, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn uv_cylindrical_map() {
+        // using a cylindrical mapping on a 3D point
+        let h = 2.0_f64.sqrt() / 2.0;
+        let scenarios = vec![
+            (pt(0.0, 0.0, -1.0), 0.0, 0.0),
+            (pt(0.0, 0.5, -1.0), 0.0, 0.5),
+            (pt(0.0, 1.0, -1.0), 0.0, 0.0),
+            (pt(h, 0.5, -h), 0.125, 0.5),
+            (pt(1.0, 0.5, 0.0), 0.25, 0.5),
+            (pt(h, 0.5, h), 0.375, 0.5),
+            (pt(0.0, -0.25, 1.0), 0.5, 0.75),
+            (pt(-h, 0.5, h), 0.625, 0.5),
+            (pt(-1.0, 1.25, 0.0), 0.75, 0.25),
+            (pt(-h, 0.5, -h), 0.875, 0.5),
+        ];
+        for (point, u, v) in scenarios {
+            let (mu, mv) = cylindrical_map(point);
+            assert!(equal(mu, u) && equal(mv, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn uv_cube_face() {
+        // identifying the face of a cube from a point
+        let scenarios = vec![
+            (pt(-1.0, 0.5, -0.25), CubeFace::Left),
+            (pt(1.1, -0.75, 0.8), CubeFace::Right),
+            (pt(0.1, 0.6, 0.9), CubeFace::Front),
+            (pt(-0.7, 0.0, -2.0), CubeFace::Back),
+            (pt(0.5, 1.0, 0.9), CubeFace::Up),
+            (pt(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+        for (point, face) in scenarios {
+            assert_eq!(CubeFace::from_point(point), face);
+        }
+    }
+
+    #[test]
+    fn uv_cube_faces() {
+        // uv mapping the front face of a cube
+        let scenarios = vec![
+            (CubeFace::Front, pt(-0.5, 0.5, 1.0), 0.25, 0.75),
+            (CubeFace::Front, pt(0.5, -0.5, 1.0), 0.75, 0.25),
+            // uv mapping the back face of a cube
+            (CubeFace::Back, pt(0.5, 0.5, -1.0), 0.25, 0.75),
+            (CubeFace::Back, pt(-0.5, -0.5, -1.0), 0.75, 0.25),
+            // uv mapping the left face of a cube
+            (CubeFace::Left, pt(-1.0, 0.5, -0.5), 0.25, 0.75),
+            (CubeFace::Left, pt(-1.0, -0.5, 0.5), 0.75, 0.25),
+            // uv mapping the right face of a cube
+            (CubeFace::Right, pt(1.0, 0.5, 0.5), 0.25, 0.75),
+            (CubeFace::Right, pt(1.0, -0.5, -0.5), 0.75, 0.25),
+            // uv mapping the upper face of a cube
+            (CubeFace::Up, pt(-0.5, 1.0, -0.5), 0.25, 0.75),
+            (CubeFace::Up, pt(0.5, 1.0, 0.5), 0.75, 0.25),
+            // uv mapping the lower face of a cube
+            (CubeFace::Down, pt(-0.5, -1.0, 0.5), 0.25, 0.75),
+            (CubeFace::Down, pt(0.5, -1.0, -0.5), 0.75, 0.25),
+        ];
+        for (face, point, u, v) in scenarios {
+            assert_eq!(face.uv(point), (u, v), "{:?} {:?}", face, point);
+        }
+    }
+
+    #[test]
+    fn uv_image_pattern() {
+        // an image pattern looks up the pixel nearest to (u, v)
+        let ppm = "P3
+10 10
+10
+0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9
+1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0
+2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1
+3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2
+4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3
+5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4
+6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5
+7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6
+8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7
+9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8
+";
+        let pattern = uv_image(Canvas::from_ppm(ppm).unwrap());
+        let scenarios = vec![
+            (0.0, 0.0, Color::new(0.9, 0.9, 0.9)),
+            (0.3, 0.0, Color::new(0.2, 0.2, 0.2)),
+            (0.6, 0.3, Color::new(0.1, 0.1, 0.1)),
+            (1.0, 1.0, Color::new(0.9, 0.9, 0.9)),
+        ];
+        for (u, v, expected) in scenarios {
+            assert_eq!(pattern.uv_pattern_at(u, v), expected);
+        }
+
+        // images are only equal when they share the same pixels
+        let image = UvImage::new(Canvas::from_ppm(ppm).unwrap());
+        assert_eq!(image.clone(), image);
+        assert_ne!(UvImage::new(Canvas::from_ppm(ppm).unwrap()), image);
+
+        // coordinates outside of the image repeat or clamp
+        assert_eq!(image.uv_pattern_at(1.3, 0.0), Color::new(0.2, 0.2, 0.2));
+        assert_eq!(image.uv_pattern_at(-0.7, -1.0), Color::new(0.2, 0.2, 0.2));
+        let image = image.wrap(TextureWrap::Clamp);
+        assert_eq!(image.uv_pattern_at(1.3, 0.0), Color::new(0.8, 0.8, 0.8));
+        assert_eq!(image.uv_pattern_at(-0.7, -1.0), Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn uv_image_bilinear() {
+        // a 2x2 image of black and white pixels
+        let ppm = "P3\n2 2\n1\n0 0 0  1 1 1\n1 1 1  0 0 0\n";
+        let image = UvImage::new(Canvas::from_ppm(ppm).unwrap()).filter(TextureFilter::Bilinear);
+
+        // sampling at a pixel center returns that pixel
+        assert_eq!(image.uv_pattern_at(0.25, 0.75), black());
+        assert_eq!(image.uv_pattern_at(0.75, 0.75), white());
+
+        // sampling between pixel centers blends them
+        let gray = Color::new(0.5, 0.5, 0.5);
+        assert_eq!(image.uv_pattern_at(0.5, 0.75), gray);
+        assert_eq!(image.uv_pattern_at(0.5, 0.5), gray);
+        assert_eq!(
+            image.uv_pattern_at(0.375, 0.75),
+            Color::new(0.25, 0.25, 0.25)
+        );
+
+        // repeating blends across the edge of the image
+        assert_eq!(image.uv_pattern_at(0.0, 0.75), gray);
+
+        // clamping stops at the edge of the image
+        let image = image.wrap(TextureWrap::Clamp);
+        assert_eq!(image.uv_pattern_at(0.0, 0.75), black());
+    }
+}